@@ -5,13 +5,13 @@ use serde::Deserialize;
 
 use crate::consumer::{
     Config,
-    battery::{BatteryColors, BatteryConfig},
+    battery::{BatteryBackend, BatteryColors, BatteryConfig, BatterySelection},
     clock::ClockConfig,
-    cpu::CpuConfig,
-    memory::MemoryConfig,
+    cpu::{CpuConfig, CpuMode},
+    memory::{MemoryConfig, MemoryDisplay, MemoryStat},
     network::NetworkConfig,
-    temp::TempConfig,
-    window_diagram::WindowDiagramConfig,
+    temp::{TempAggregate, TempConfig, TempUnit},
+    window_diagram::{DiagramWorkspaces, WindowDiagramConfig},
     window_title::WindowTitleConfig,
     workspace::WorkspaceConfig,
 };
@@ -32,6 +32,13 @@ pub struct GlobalConfig {
     pub background: Color,
     pub font_size: f32,
     pub spacing: f32,
+    /// How far below the bar popups may extend. The bar's surface grows by
+    /// this much while a popup is open.
+    pub popup_height: u32,
+    /// Background while niri's overview is open, instead of `background`.
+    pub overview_background: Option<Color>,
+    /// Hides the bar while niri's overview is open.
+    pub hide_in_overview: bool,
     #[serde(skip)]
     pub output: Option<String>,
 }
@@ -53,6 +60,9 @@ impl Default for RustybarConfig {
                 background: Color::BLACK,
                 font_size: 18.0,
                 spacing: 12.0,
+                popup_height: 400,
+                overview_background: None,
+                hide_in_overview: false,
                 output: None,
             },
             left: vec![
@@ -71,6 +81,7 @@ impl Default for RustybarConfig {
                     background: Color::BLACK,
                     urgent: Color::from_str("#ffbf00").unwrap(),
                     visible: Color::from_str("#666666").unwrap(),
+                    workspaces: DiagramWorkspaces::Active,
                 }),
                 Box::new(WindowTitleConfig { color: blue }),
             ],
@@ -79,12 +90,22 @@ impl Default for RustybarConfig {
                     colormap: [(40.0, aqua), (60.0, blue), (80.0, magenta), (100.0, red)]
                         .iter()
                         .collect(),
+                    sensors: None,
+                    aggregate: TempAggregate::Max,
+                    unit: TempUnit::Celsius,
+                    critical_color: Some(red),
+                    label_color: Color::from_str("#aaaaaa").unwrap(),
+                    spacing: 10.0,
+                    graph: None,
                 }),
                 Box::new(CpuConfig {
                     min_max_width: 40.0,
                     avg_width: 80.0,
                     spacing: 10.0,
                     height: 16.0,
+                    mode: CpuMode::MinAvgMax,
+                    core_width: 8.0,
+                    graph: None,
                     colormap: [
                         (0.0, bg2),
                         (0.2, bg1),
@@ -99,6 +120,12 @@ impl Default for RustybarConfig {
                     colormap: [(1e9, red), (3e9, magenta), (6e9, blue), (8e9, aqua)]
                         .iter()
                         .collect(),
+                    stat: MemoryStat::Available,
+                    display: MemoryDisplay::Bytes,
+                    width: 40.0,
+                    height: 16.0,
+                    spacing: 4.0,
+                    graph: None,
                 }),
             ],
             right: vec![
@@ -114,6 +141,13 @@ impl Default for RustybarConfig {
                     .iter()
                     .collect(),
                     spacing: 20.0,
+                    interfaces: vec![],
+                    per_interface: false,
+                    show_address: false,
+                    hide_down: false,
+                    label_color: Color::from_str("#aaaaaa").unwrap(),
+                    down_color: None,
+                    graph: None,
                 }),
                 Box::new(BatteryConfig {
                     width: 40.0,
@@ -123,7 +157,15 @@ impl Default for RustybarConfig {
                         charge: aqua,
                         discharge: red,
                         unknown: magenta,
+                        text: Color::from_str("#aaaaaa").unwrap(),
                     },
+                    backend: BatteryBackend::Sysfs,
+                    battery: BatterySelection::Combined,
+                    show_time: false,
+                    show_power: false,
+                    show_health: false,
+                    alerts: None,
+                    peripherals: false,
                     colormap: [(0.0, red), (0.3, magenta), (0.7, blue), (1.0, aqua)]
                         .iter()
                         .collect(),
@@ -1,13 +1,37 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
 use async_trait::async_trait;
-use iced::Element;
+use futures::future::BoxFuture;
+use iced::{Element, Size, alignment::Horizontal, mouse::ScrollDelta};
 use iced_layershell::to_layer_message;
 
+pub mod backlight;
 pub mod battery;
+pub mod bluetooth;
 pub mod clock;
+pub mod config_error;
 pub mod cpu;
+pub mod disk;
+pub mod disk_io;
+pub mod dnd;
+pub mod keyboard_layout;
 pub mod memory;
+pub mod mpris;
 pub mod network;
+pub mod networkmanager;
+pub mod notifications;
+pub mod system;
+pub mod taskbar;
 pub mod temp;
+pub mod tray;
+pub mod volume;
+pub mod wifi;
 pub mod window_diagram;
 pub mod window_title;
 pub mod workspace;
@@ -17,6 +41,61 @@ pub mod workspace;
 pub enum IcedMessage {
     A,
     Exit,
+    Action(Action),
+    /// Opens the popup, or closes it if it's already open.
+    TogglePopup(PopupId),
+    ClosePopup,
+    /// Several messages, handled in order.
+    Batch(Vec<IcedMessage>),
+    /// The bar was laid out at this size.
+    BarResized(Size),
+    /// The toasts beneath the bar were laid out at this size, with this
+    /// alignment.
+    ToastsResized(Size, Horizontal),
+}
+
+/// Identifies a popup. Consumers make one for each popup they can show, and
+/// render it when asked through [`Consumer::popup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PopupId(usize);
+
+impl PopupId {
+    pub fn unique() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        PopupId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Work a consumer wants done in response to input, such as a D-Bus call.
+/// The bar runs it in the background, then redraws.
+#[derive(Clone)]
+pub struct Action(Arc<dyn Fn() -> BoxFuture<'static, ()> + Send + Sync>);
+
+impl Action {
+    pub fn new<F, Fut>(f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Action(Arc::new(move || Box::pin(f())))
+    }
+
+    pub fn run(&self) -> BoxFuture<'static, ()> {
+        (self.0)()
+    }
+}
+
+impl fmt::Debug for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Action")
+    }
+}
+
+/// Which way a scroll went: 1.0 for up, -1.0 for down, or `None` for
+/// horizontal scrolling.
+pub fn scroll_direction(delta: ScrollDelta) -> Option<f32> {
+    let (ScrollDelta::Lines { y, .. } | ScrollDelta::Pixels { y, .. }) = delta;
+    (y != 0.0).then(|| y.signum())
 }
 
 #[async_trait]
@@ -26,6 +105,18 @@ pub trait Consumer: Send + Sync + 'static {
     async fn consume(&mut self);
 
     fn render(&self, output: &str) -> Element<'_, IcedMessage>;
+
+    /// The contents of popup `id`, if it belongs to this consumer. Shown
+    /// beneath the bar, on the side of the consumer's section.
+    fn popup(&self, _id: PopupId, _output: &str) -> Option<Element<'_, IcedMessage>> {
+        None
+    }
+
+    /// Content to show beneath the bar without being asked, like
+    /// notifications. Hidden while a popup is open.
+    fn toasts(&self, _output: &str) -> Option<Element<'_, IcedMessage>> {
+        None
+    }
 }
 
 #[typetag::serde(tag = "type")]
@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element, Length, Theme,
+    alignment::Vertical,
+    border::Radius,
+    widget::{ProgressBar, mouse_area, row, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, IcedMessage, scroll_direction},
+    producer::backlight::{self, Backlight},
+};
+
+use super::Consumer;
+
+#[derive(Deserialize, Serialize)]
+pub struct BacklightConfig {
+    /// The backlight to show, e.g. "intel_backlight". When unset, the first
+    /// one is used.
+    #[serde(default)]
+    pub device: Option<String>,
+    pub color: Color,
+    pub width: f32,
+    pub height: f32,
+    pub spacing: f32,
+    /// How much one scroll step changes the brightness, as a fraction of the
+    /// maximum.
+    #[serde(default = "default_step")]
+    pub step: f32,
+    #[serde(default)]
+    pub show_percent: bool,
+}
+
+fn default_step() -> f32 {
+    0.05
+}
+
+#[typetag::serde]
+impl Config for BacklightConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = backlight::listen();
+
+        Box::new(BacklightConsumer {
+            receiver,
+            config: *self,
+        })
+    }
+}
+
+pub struct BacklightConsumer {
+    receiver: watch::Receiver<backlight::Message>,
+    config: BacklightConfig,
+}
+
+impl BacklightConsumer {
+    fn bar(&self, brightness: f32) -> ProgressBar<'_, Theme> {
+        let color = self.config.color;
+        iced::widget::progress_bar(0.0..=1.0, brightness)
+            .length(Length::Fixed(self.config.width))
+            .girth(Length::Fixed(self.config.height))
+            .style(move |theme: &Theme| iced::widget::progress_bar::Style {
+                bar: color.into(),
+                border: iced::Border {
+                    color,
+                    width: 1.0,
+                    radius: Radius::new(0.0),
+                },
+                background: theme.palette().background.into(),
+            })
+    }
+
+    fn backlight(&self, backlight: &Backlight) -> Element<'_, IcedMessage> {
+        let fraction = backlight.fraction();
+        let percent = self
+            .config
+            .show_percent
+            .then(|| text(format!("{:.0}%", fraction * 100.0)).color(self.config.color));
+        let content = row![self.bar(fraction), percent]
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing);
+
+        let name = backlight.name.clone();
+        let step = self.config.step;
+        mouse_area(content)
+            .on_scroll(move |delta| match scroll_direction(delta) {
+                Some(direction) => {
+                    let name = name.clone();
+                    IcedMessage::Action(Action::new(move || {
+                        backlight::adjust(name.clone(), direction * step)
+                    }))
+                }
+                None => IcedMessage::A,
+            })
+            .into()
+    }
+}
+
+#[async_trait]
+impl Consumer for BacklightConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let backlight = match &self.config.device {
+            Some(name) => msg.devices.iter().find(|b| b.name == *name),
+            None => msg.devices.first(),
+        };
+        match backlight {
+            Some(backlight) => self.backlight(backlight),
+            None => row![].into(),
+        }
+    }
+}
@@ -1,17 +1,25 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use iced::{
     Color, Element, Length, Theme,
     alignment::Vertical,
     border::Radius,
-    widget::{ProgressBar, row, text},
+    widget::{ProgressBar, Row, row, text},
 };
 use serde::{Deserialize, Serialize};
 use tokio::sync::watch;
 
 use crate::{
     consumer::{Config, IcedMessage},
-    producer::tick::{self},
-    util::color::Colormap,
+    producer::{
+        tick::{self, Battery},
+        upower,
+    },
+    util::{
+        color::Colormap,
+        notify::{Urgency, notify},
+    },
 };
 
 use super::Consumer;
@@ -23,15 +31,168 @@ pub struct BatteryConfig {
     pub spacing: f32,
     pub colormap: Colormap,
     pub colors: BatteryColors,
+    #[serde(default)]
+    pub backend: BatteryBackend,
+    #[serde(default)]
+    pub battery: BatterySelection,
+    /// Show the time until full when charging, or until empty when
+    /// discharging.
+    #[serde(default)]
+    pub show_time: bool,
+    /// Show the rate of charge or discharge, in watts.
+    #[serde(default)]
+    pub show_power: bool,
+    /// Show full capacity as a percentage of design capacity.
+    #[serde(default)]
+    pub show_health: bool,
+    #[serde(default)]
+    pub alerts: Option<BatteryAlerts>,
+    /// Also show peripherals' batteries (mice, headsets, ...), labeled with
+    /// their model. Only the UPower backend knows about peripherals.
+    #[serde(default)]
+    pub peripherals: bool,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BatteryBackend {
+    /// Read batteries from sysfs every tick.
+    #[default]
+    Sysfs,
+    /// Follow UPower on the system bus, which updates as soon as the battery
+    /// state changes.
+    Upower,
+}
+
+/// Warnings for a discharging battery running low. Levels are checked
+/// against all batteries combined.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct BatteryAlerts {
+    /// Below this charge, the battery bar blinks.
+    pub low: f32,
+    /// Below this charge, `critical_command` is run.
+    pub critical: f32,
+    /// The color the bar blinks to.
+    pub blink_color: Color,
+    /// Send a desktop notification when the charge drops below `low` and
+    /// `critical`.
+    #[serde(default)]
+    pub notify: bool,
+    /// Run once each time the charge drops below `critical`, e.g.
+    /// `["systemctl", "suspend"]`.
+    #[serde(default)]
+    pub critical_command: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum AlertLevel {
+    Normal,
+    Low,
+    Critical,
+}
+
+impl BatteryAlerts {
+    fn level(&self, battery: &Battery) -> AlertLevel {
+        if battery.state != starship_battery::State::Discharging {
+            AlertLevel::Normal
+        } else if battery.charge < self.critical {
+            AlertLevel::Critical
+        } else if battery.charge < self.low {
+            AlertLevel::Low
+        } else {
+            AlertLevel::Normal
+        }
+    }
+
+    /// Moves `level` on to the battery's, returning the new level if it
+    /// rose and should be alerted. Charging, or losing the battery, re-arms
+    /// the alerts.
+    fn step(&self, level: &mut AlertLevel, battery: Option<&Battery>) -> Option<AlertLevel> {
+        let new = battery.map_or(AlertLevel::Normal, |battery| self.level(battery));
+        let rose = new > *level;
+        *level = new;
+        rose.then_some(new)
+    }
+
+    /// Fires notifications and the critical command whenever the alert level
+    /// rises. Runs in the background, so alerts don't depend on the bar
+    /// being redrawn. `battery` picks the combined battery out of each
+    /// message.
+    fn watch<T: Send + Sync + 'static>(
+        self,
+        mut receiver: watch::Receiver<T>,
+        battery: fn(&T) -> Option<Battery>,
+    ) {
+        tokio::spawn(async move {
+            let mut level = AlertLevel::Normal;
+            loop {
+                let battery = battery(&receiver.borrow_and_update());
+                if let Some(new) = self.step(&mut level, battery.as_ref()) {
+                    let charge = battery.map_or(1.0, |battery| battery.charge);
+                    self.alert(new, charge).await;
+                }
+
+                if receiver.changed().await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    async fn alert(&self, level: AlertLevel, charge: f32) {
+        let percent = format!("{:.0}% remaining", charge * 100.0);
+        let (summary, urgency) = match level {
+            AlertLevel::Normal => return,
+            AlertLevel::Low => ("Battery low", Urgency::Normal),
+            AlertLevel::Critical => ("Battery critical", Urgency::Critical),
+        };
+
+        if self.notify
+            && let Err(e) = notify(summary, &percent, urgency).await
+        {
+            eprintln!("battery: failed to send notification: {e}");
+        }
+
+        if level == AlertLevel::Critical
+            && let [program, args @ ..] = self.critical_command.as_slice()
+            && let Err(e) = tokio::process::Command::new(program).args(args).spawn()
+        {
+            eprintln!("battery: failed to run critical command {program:?}: {e}");
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BatterySelection {
+    /// All batteries as one.
+    #[default]
+    Combined,
+    /// Each battery separately.
+    Each,
+    /// Only the battery at this index, in the order the system enumerates
+    /// them.
+    Index(usize),
 }
 
 #[typetag::serde]
 impl Config for BatteryConfig {
     fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
         let receiver = tick::listen();
+        let upower = match self.backend {
+            BatteryBackend::Sysfs => None,
+            BatteryBackend::Upower => Some(upower::listen()),
+        };
+        if let Some(alerts) = self.alerts.clone() {
+            match &upower {
+                None => alerts.watch(tick::listen(), |msg| msg.battery.clone()),
+                Some(upower) => alerts.watch(upower.clone(), combined),
+            }
+        }
 
         Box::new(BatteryConsumer {
             receiver,
+            upower,
             config: *self,
         })
     }
@@ -42,16 +203,28 @@ pub struct BatteryColors {
     pub charge: Color,
     pub discharge: Color,
     pub unknown: Color,
+    /// Color of the time, power and health text, and of peripheral names.
+    pub text: Color,
+}
+
+/// UPower's system batteries as one.
+fn combined(msg: &upower::Message) -> Option<Battery> {
+    Battery::combine(&msg.system_batteries().cloned().collect::<Vec<_>>())
 }
 
 pub struct BatteryConsumer {
+    /// Always followed, for the blink phase.
     receiver: watch::Receiver<tick::Message>,
+    upower: Option<watch::Receiver<upower::Message>>,
     config: BatteryConfig,
 }
 
 impl BatteryConsumer {
-    fn bar(&self, charge: f32) -> ProgressBar<'_, Theme> {
-        let color = self.config.colormap.map(charge);
+    fn bar(&self, charge: f32, blink: bool) -> ProgressBar<'_, Theme> {
+        let color = match &self.config.alerts {
+            Some(alerts) if blink => alerts.blink_color,
+            _ => self.config.colormap.map(charge),
+        };
         iced::widget::progress_bar(0.0..=1.0, charge)
             .length(Length::Fixed(self.config.width))
             .girth(Length::Fixed(self.config.height))
@@ -65,6 +238,63 @@ impl BatteryConsumer {
                 background: theme.palette().background.into(),
             })
     }
+
+    /// Renders `battery`, in the blink color if `blink` is set.
+    fn battery(&self, battery: &Battery, blink: bool) -> Element<'_, IcedMessage> {
+        let colors = &self.config.colors;
+        let state = match battery.state {
+            starship_battery::State::Unknown => text('*').color(colors.unknown),
+            starship_battery::State::Charging => text('+').color(colors.charge),
+            starship_battery::State::Discharging => text('-').color(colors.discharge),
+            starship_battery::State::Empty => text('!').color(colors.unknown),
+            starship_battery::State::Full => text(' '),
+        };
+
+        let details = |t: String| text(t).color(colors.text);
+        let time = match battery.state {
+            starship_battery::State::Charging => battery.time_to_full,
+            starship_battery::State::Discharging => battery.time_to_empty,
+            _ => None,
+        };
+        let time = time.filter(|_| self.config.show_time).map(format_duration);
+        let power = (self.config.show_power && battery.power > 0.0)
+            .then(|| format!("{:.1} W", battery.power));
+        let health = self
+            .config
+            .show_health
+            .then(|| format!("{:.0}%", battery.health() * 100.0));
+
+        row![
+            self.bar(battery.charge, blink),
+            state,
+            time.map(details),
+            power.map(details),
+            health.map(details),
+        ]
+        .align_y(Vertical::Center)
+        .spacing(self.config.spacing)
+        .into()
+    }
+
+    fn peripheral(&self, device: &upower::Device) -> Element<'_, IcedMessage> {
+        let label = match &device.battery.name {
+            Some(name) => name.clone(),
+            None => device.kind.name().to_string(),
+        };
+        row![
+            text(label).color(self.config.colors.text),
+            self.bar(device.battery.charge, false),
+        ]
+        .align_y(Vertical::Center)
+        .spacing(self.config.spacing / 2.0)
+        .into()
+    }
+}
+
+/// Formats a duration as hours and minutes, e.g. "2:05".
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
 }
 
 #[async_trait]
@@ -74,22 +304,97 @@ impl Consumer for BatteryConsumer {
     }
 
     fn render(&self, _: &str) -> Element<'_, IcedMessage> {
-        let Some(battery) = &self.receiver.borrow().battery else {
-            return row![].into();
+        let msg = self.receiver.borrow();
+        let upower = self.upower.as_ref().map(|u| u.borrow());
+        let (combined, all): (Option<Battery>, Vec<&Battery>) = match &upower {
+            None => (msg.battery.clone(), msg.batteries.iter().collect()),
+            Some(upower) => (combined(upower), upower.system_batteries().collect()),
         };
-
-        let colors = &self.config.colors;
-        let text = match battery.state {
-            starship_battery::State::Unknown => text('*').color(colors.unknown),
-            starship_battery::State::Charging => text('+').color(colors.charge),
-            starship_battery::State::Discharging => text('-').color(colors.discharge),
-            starship_battery::State::Empty => text('!').color(colors.unknown),
-            starship_battery::State::Full => text(' '),
+        let batteries: Vec<&Battery> = match self.config.battery {
+            BatterySelection::Combined => combined.iter().collect(),
+            BatterySelection::Each => all,
+            BatterySelection::Index(i) => all.get(i).copied().into_iter().collect(),
         };
+        let peripherals = upower
+            .iter()
+            .filter(|_| self.config.peripherals)
+            .flat_map(|upower| upower.peripherals())
+            .map(|device| self.peripheral(device));
 
-        row![self.bar(battery.charge), text]
+        // Alerts go by the batteries combined, whichever are shown, and
+        // blink every other tick.
+        let alert = self.config.alerts.as_ref().is_some_and(|alerts| {
+            combined
+                .as_ref()
+                .is_some_and(|b| alerts.level(b) != AlertLevel::Normal)
+        });
+        let blink = alert && msg.time.second() % 2 == 1;
+        let batteries = batteries.into_iter().map(|b| self.battery(b, blink));
+        Row::with_children(batteries.chain(peripherals))
             .align_y(Vertical::Center)
             .spacing(self.config.spacing)
             .into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use starship_battery::State;
+
+    use super::*;
+
+    fn alerts() -> BatteryAlerts {
+        BatteryAlerts {
+            low: 0.15,
+            critical: 0.05,
+            blink_color: Color::BLACK,
+            notify: false,
+            critical_command: vec![],
+        }
+    }
+
+    fn battery(state: State, charge: f32) -> Battery {
+        Battery {
+            state,
+            charge,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn alerts_each_level_once() {
+        let alerts = alerts();
+        let mut level = AlertLevel::Normal;
+        let mut step = |state, charge| alerts.step(&mut level, Some(&battery(state, charge)));
+
+        assert_eq!(step(State::Discharging, 0.5), None);
+        assert_eq!(step(State::Discharging, 0.14), Some(AlertLevel::Low));
+        assert_eq!(step(State::Discharging, 0.10), None);
+        assert_eq!(step(State::Discharging, 0.04), Some(AlertLevel::Critical));
+        assert_eq!(step(State::Discharging, 0.03), None);
+    }
+
+    #[test]
+    fn charging_rearms_alerts() {
+        let alerts = alerts();
+        let mut level = AlertLevel::Normal;
+        let mut step = |state, charge| alerts.step(&mut level, Some(&battery(state, charge)));
+
+        assert_eq!(step(State::Discharging, 0.04), Some(AlertLevel::Critical));
+        assert_eq!(step(State::Charging, 0.04), None);
+        assert_eq!(step(State::Discharging, 0.04), Some(AlertLevel::Critical));
+        assert_eq!(step(State::Charging, 0.2), None);
+        assert_eq!(step(State::Discharging, 0.14), Some(AlertLevel::Low));
+    }
+
+    #[test]
+    fn losing_the_battery_rearms_alerts() {
+        let alerts = alerts();
+        let mut level = AlertLevel::Normal;
+
+        let low = battery(State::Discharging, 0.1);
+        assert_eq!(alerts.step(&mut level, Some(&low)), Some(AlertLevel::Low));
+        assert_eq!(alerts.step(&mut level, None), None);
+        assert_eq!(alerts.step(&mut level, Some(&low)), Some(AlertLevel::Low));
+    }
+}
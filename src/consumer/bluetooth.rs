@@ -0,0 +1,132 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element,
+    alignment::Vertical,
+    widget::{Column, Row, button, column, mouse_area, row, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, IcedMessage, PopupId},
+    producer::bluetooth::{self, Command, Device},
+};
+
+use super::Consumer;
+
+/// Shows whether Bluetooth is on, and the connected devices with their
+/// battery levels. Click to turn Bluetooth on or off; right click for the
+/// paired devices, to connect or disconnect them.
+#[derive(Deserialize, Serialize)]
+pub struct BluetoothConfig {
+    pub color: Color,
+    /// Color when powered off, and of devices that aren't connected.
+    #[serde(default = "default_label_color")]
+    pub label_color: Color,
+    pub spacing: f32,
+}
+
+fn default_label_color() -> Color {
+    Color::from_rgb8(0xaa, 0xaa, 0xaa)
+}
+
+#[typetag::serde]
+impl Config for BluetoothConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = bluetooth::listen();
+
+        Box::new(BluetoothConsumer {
+            receiver,
+            devices_popup: PopupId::unique(),
+            config: *self,
+        })
+    }
+}
+
+pub struct BluetoothConsumer {
+    receiver: watch::Receiver<bluetooth::Message>,
+    devices_popup: PopupId,
+    config: BluetoothConfig,
+}
+
+fn command(command: Command) -> IcedMessage {
+    IcedMessage::Action(Action::new(move || bluetooth::command(command.clone())))
+}
+
+/// A device's name, with its battery level if it has one.
+fn label(device: &Device) -> String {
+    match device.battery {
+        Some(battery) => format!("{} {battery}%", device.name),
+        None => device.name.clone(),
+    }
+}
+
+#[async_trait]
+impl Consumer for BluetoothConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let Some(adapter) = msg.adapters.first() else {
+            return row![].into();
+        };
+
+        let color = if adapter.powered {
+            self.config.color
+        } else {
+            self.config.label_color
+        };
+        let connected = msg
+            .devices_of(adapter)
+            .filter(|d| d.connected)
+            .map(|d| text(label(d)).color(self.config.color).into());
+        mouse_area(
+            row![text("bt").color(color), Row::with_children(connected)]
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing / 2.0),
+        )
+        .on_press(command(Command::SetPowered(
+            adapter.path.clone(),
+            !adapter.powered,
+        )))
+        .on_right_press(IcedMessage::TogglePopup(self.devices_popup))
+        .into()
+    }
+
+    fn popup(&self, id: PopupId, _: &str) -> Option<Element<'_, IcedMessage>> {
+        if id != self.devices_popup {
+            return None;
+        }
+        let msg = self.receiver.borrow();
+        let adapter = msg.adapters.first()?;
+
+        let power = if adapter.powered {
+            "Turn off"
+        } else {
+            "Turn on"
+        };
+        let power = button(text(format!("{}: {power}", adapter.name)))
+            .style(button::text)
+            .on_press(command(Command::SetPowered(
+                adapter.path.clone(),
+                !adapter.powered,
+            )));
+        let devices = msg.devices_of(adapter).filter(|d| d.paired).map(|device| {
+            let (color, toggle) = if device.connected {
+                (self.config.color, Command::Disconnect(device.path.clone()))
+            } else {
+                (
+                    self.config.label_color,
+                    Command::Connect(device.path.clone()),
+                )
+            };
+            button(text(label(device)).color(color))
+                .style(button::text)
+                .on_press_maybe(adapter.powered.then(|| command(toggle)))
+                .into()
+        });
+        Some(column![power, Column::with_children(devices)].into())
+    }
+}
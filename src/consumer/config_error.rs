@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element,
+    widget::{row, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Config, IcedMessage},
+    producer::niri,
+};
+
+use super::Consumer;
+
+/// Warns while niri's config fails to load, and shows nothing otherwise.
+#[derive(Deserialize, Serialize)]
+pub struct ConfigErrorConfig {
+    #[serde(default = "default_text")]
+    pub text: String,
+    pub color: Color,
+}
+
+fn default_text() -> String {
+    "niri config error".into()
+}
+
+#[typetag::serde]
+impl Config for ConfigErrorConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = niri::listen();
+
+        Box::new(ConfigErrorConsumer {
+            receiver,
+            config: *self,
+        })
+    }
+}
+
+pub struct ConfigErrorConsumer {
+    receiver: watch::Receiver<niri::Message>,
+    config: ConfigErrorConfig,
+}
+
+#[async_trait]
+impl Consumer for ConfigErrorConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        if !self.receiver.borrow().config_failed {
+            return row![].into();
+        }
+        text(self.config.text.clone())
+            .color(self.config.color)
+            .into()
+    }
+}
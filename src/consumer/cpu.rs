@@ -3,7 +3,7 @@ use iced::{
     Element, Length, Theme,
     alignment::Vertical,
     border::Radius,
-    widget::{ProgressBar, row},
+    widget::{ProgressBar, Row, container, row},
 };
 use serde::{Deserialize, Serialize};
 use tokio::sync::watch;
@@ -11,7 +11,10 @@ use tokio::sync::watch;
 use crate::{
     consumer::{Config, IcedMessage},
     producer::tick::{self},
-    util::color::Colormap,
+    util::{
+        color::Colormap,
+        graph::{Graph, GraphConfig},
+    },
 };
 
 use super::Consumer;
@@ -23,15 +26,45 @@ pub struct CpuConfig {
     pub colormap: Colormap,
     pub spacing: f32,
     pub height: f32,
+    #[serde(default)]
+    pub mode: CpuMode,
+    /// Width of each core's bar or cell in the per-core modes.
+    #[serde(default = "default_core_width")]
+    pub core_width: f32,
+    /// If set, a history graph of the average usage is drawn after the bars.
+    #[serde(default)]
+    pub graph: Option<GraphConfig>,
+}
+
+fn default_core_width() -> f32 {
+    8.0
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum CpuMode {
+    /// Bars for the least busy core, the average, and the busiest core.
+    #[default]
+    MinAvgMax,
+    /// One bar per logical core.
+    Cores,
+    /// One bar per physical core, averaging over its hardware threads.
+    PhysicalCores,
+    /// A strip of cells, one per logical core, colored by its usage.
+    Heat,
 }
 
 #[typetag::serde]
 impl Config for CpuConfig {
     fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
         let receiver = tick::listen();
+        let graph = self.graph.clone().map(|g| {
+            Graph::new(g, tick::listen(), |msg| msg.cpu.avg).default_range(Some(0.0), Some(1.0))
+        });
 
         Box::new(CpuConsumer {
             receiver,
+            graph,
             config: *self,
         })
     }
@@ -39,6 +72,7 @@ impl Config for CpuConfig {
 
 pub struct CpuConsumer {
     receiver: watch::Receiver<tick::Message>,
+    graph: Option<Graph>,
     config: CpuConfig,
 }
 
@@ -58,6 +92,29 @@ impl CpuConsumer {
                 background: theme.palette().background.into(),
             })
     }
+
+    fn core_bars(&self, cores: &[f32]) -> Element<'_, IcedMessage> {
+        Row::with_children(
+            cores
+                .iter()
+                .map(|&c| self.bar(c, self.config.core_width).into()),
+        )
+        .align_y(Vertical::Center)
+        .spacing(self.config.spacing)
+        .into()
+    }
+
+    fn cell(&self, value: f32) -> Element<'_, IcedMessage> {
+        let color = self.config.colormap.map(value);
+        container(row![])
+            .width(Length::Fixed(self.config.core_width))
+            .height(Length::Fixed(self.config.height))
+            .style(move |_| container::Style {
+                background: Some(color.into()),
+                ..Default::default()
+            })
+            .into()
+    }
 }
 
 #[async_trait]
@@ -68,13 +125,29 @@ impl Consumer for CpuConsumer {
 
     fn render(&self, _: &str) -> Element<'_, IcedMessage> {
         let cpu = &self.receiver.borrow().cpu;
-        row![
-            self.bar(cpu.min, self.config.min_max_width),
-            self.bar(cpu.avg, self.config.avg_width),
-            self.bar(cpu.max, self.config.min_max_width),
-        ]
-        .align_y(Vertical::Center)
-        .spacing(self.config.spacing)
-        .into()
+        let usage: Element<'_, IcedMessage> = match self.config.mode {
+            CpuMode::MinAvgMax => row![
+                self.bar(cpu.min, self.config.min_max_width),
+                self.bar(cpu.avg, self.config.avg_width),
+                self.bar(cpu.max, self.config.min_max_width),
+            ]
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing)
+            .into(),
+            CpuMode::Cores => self.core_bars(&cpu.cores),
+            CpuMode::PhysicalCores => self.core_bars(&cpu.physical_cores),
+            CpuMode::Heat => Row::with_children(cpu.cores.iter().map(|&c| self.cell(c)))
+                .align_y(Vertical::Center)
+                .spacing(1)
+                .into(),
+        };
+
+        match &self.graph {
+            Some(graph) => row![usage, graph.view(&self.config.colormap)]
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing)
+                .into(),
+            None => usage,
+        }
     }
 }
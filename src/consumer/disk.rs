@@ -0,0 +1,182 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use iced::{
+    Color, Element, Length, Theme,
+    alignment::Vertical,
+    border::Radius,
+    widget::{Column, ProgressBar, Row, mouse_area, row, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Config, IcedMessage, PopupId},
+    producer::tick::{self, Disk},
+    util::{bytes::format_bytes, color::Colormap},
+};
+
+use super::Consumer;
+
+#[derive(Deserialize, Serialize)]
+pub struct DiskConfig {
+    /// Mount points to show, in order. Ones that aren't mounted are skipped.
+    #[serde(default = "default_mounts")]
+    pub mounts: Vec<String>,
+    /// Keyed by the fraction of space used, from 0.0 to 1.0.
+    pub colormap: Colormap,
+    #[serde(default)]
+    pub display: DiskDisplay,
+    /// Label each disk with its mount point.
+    #[serde(default)]
+    pub show_mount: bool,
+    /// Color of mount point labels.
+    #[serde(default = "default_label_color")]
+    pub label_color: Color,
+    /// The size of the bar in the `bar` display.
+    #[serde(default = "default_width")]
+    pub width: f32,
+    #[serde(default = "default_height")]
+    pub height: f32,
+    pub spacing: f32,
+}
+
+fn default_mounts() -> Vec<String> {
+    vec!["/".into()]
+}
+
+fn default_label_color() -> Color {
+    Color::from_rgb8(0xaa, 0xaa, 0xaa)
+}
+
+fn default_width() -> f32 {
+    40.0
+}
+
+fn default_height() -> f32 {
+    16.0
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DiskDisplay {
+    /// Free space, in bytes.
+    #[default]
+    Free,
+    /// Used space, in bytes.
+    Used,
+    /// A bar of the fraction of space used.
+    Bar,
+}
+
+#[typetag::serde]
+impl Config for DiskConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        tick::read_disks();
+        let receiver = tick::listen();
+
+        Box::new(DiskConsumer {
+            receiver,
+            mounts_popup: PopupId::unique(),
+            config: *self,
+        })
+    }
+}
+
+pub struct DiskConsumer {
+    receiver: watch::Receiver<tick::Message>,
+    /// Lists every mounted filesystem.
+    mounts_popup: PopupId,
+    config: DiskConfig,
+}
+
+fn fraction_used(disk: &Disk) -> f32 {
+    if disk.total == 0 {
+        0.0
+    } else {
+        disk.used() as f32 / disk.total as f32
+    }
+}
+
+impl DiskConsumer {
+    fn bar(&self, used: f32) -> ProgressBar<'_, Theme> {
+        let color = self.config.colormap.map(used);
+        iced::widget::progress_bar(0.0..=1.0, used)
+            .length(Length::Fixed(self.config.width))
+            .girth(Length::Fixed(self.config.height))
+            .style(move |theme: &Theme| iced::widget::progress_bar::Style {
+                bar: color.into(),
+                border: iced::Border {
+                    color,
+                    width: 1.0,
+                    radius: Radius::new(0.0),
+                },
+                background: theme.palette().background.into(),
+            })
+    }
+
+    fn disk(&self, disk: &Disk) -> Element<'_, IcedMessage> {
+        let used = fraction_used(disk);
+        let color = self.config.colormap.map(used);
+        let value: Element<'_, IcedMessage> = match self.config.display {
+            DiskDisplay::Free => text(format_bytes(disk.available)).color(color).into(),
+            DiskDisplay::Used => text(format_bytes(disk.used())).color(color).into(),
+            DiskDisplay::Bar => self.bar(used).into(),
+        };
+        let label = self.config.show_mount.then(|| {
+            text(disk.mount_point.to_string_lossy().into_owned()).color(self.config.label_color)
+        });
+        row![label, value]
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing / 2.0)
+            .into()
+    }
+
+    /// Every mounted filesystem with its usage, one per line.
+    fn all_mounts(&self, disks: &[Disk]) -> Element<'_, IcedMessage> {
+        let lines = disks.iter().map(|disk| {
+            let used = fraction_used(disk);
+            text(format!(
+                "{}  {} / {} ({:.0}%)",
+                disk.mount_point.display(),
+                format_bytes(disk.used()),
+                format_bytes(disk.total),
+                used * 100.0
+            ))
+            .color(self.config.colormap.map(used))
+            .into()
+        });
+        Column::with_children(lines).into()
+    }
+}
+
+#[async_trait]
+impl Consumer for DiskConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let disks = &self.receiver.borrow().disks;
+        let shown = self.config.mounts.iter().filter_map(|mount| {
+            disks
+                .iter()
+                .find(|d| d.mount_point == Path::new(mount))
+                .map(|d| self.disk(d))
+        });
+        mouse_area(
+            Row::with_children(shown)
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing),
+        )
+        .on_press(IcedMessage::TogglePopup(self.mounts_popup))
+        .into()
+    }
+
+    fn popup(&self, id: PopupId, _: &str) -> Option<Element<'_, IcedMessage>> {
+        if id != self.mounts_popup {
+            return None;
+        }
+        Some(self.all_mounts(&self.receiver.borrow().disks))
+    }
+}
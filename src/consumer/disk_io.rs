@@ -0,0 +1,177 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element,
+    alignment::Vertical,
+    widget::{Row, Text, row, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Config, IcedMessage},
+    producer::{diskstats::DiskIo, tick},
+    util::{
+        bytes::format_bytes,
+        color::Colormap,
+        glob::Glob,
+        graph::{Graph, GraphConfig},
+    },
+};
+
+use super::Consumer;
+
+/// Devices never selected by default: they're backed by memory or files, so
+/// their traffic is either not disk I/O or counted again on a real disk.
+const VIRTUAL_DEVICES: [&str; 3] = ["loop*", "ram*", "zram*"];
+
+#[derive(Deserialize, Serialize)]
+pub struct DiskIoConfig {
+    /// Keyed by bytes per second.
+    pub colormap: Colormap,
+    pub spacing: f32,
+    /// Devices to show, by name or glob (e.g. "nvme*"). When empty, all whole
+    /// devices are shown, except loop, RAM and zram devices.
+    #[serde(default)]
+    pub devices: Vec<String>,
+    /// Show each selected device separately, labeled with its name, instead
+    /// of summing them.
+    #[serde(default)]
+    pub per_device: bool,
+    /// Color of device names.
+    #[serde(default = "default_label_color")]
+    pub label_color: Color,
+    /// If set, history graphs of the summed read and write rates are drawn
+    /// after their text.
+    #[serde(default)]
+    pub graph: Option<GraphConfig>,
+}
+
+fn default_label_color() -> Color {
+    Color::from_rgb8(0xaa, 0xaa, 0xaa)
+}
+
+/// Which devices a disk I/O module covers.
+#[derive(Clone)]
+struct Selection {
+    globs: Vec<Glob>,
+    excluded: Vec<Glob>,
+}
+
+impl Selection {
+    fn new(devices: &[String]) -> Self {
+        Selection {
+            globs: devices.iter().map(|d| Glob::new(d)).collect(),
+            excluded: VIRTUAL_DEVICES.iter().map(|d| Glob::new(d)).collect(),
+        }
+    }
+
+    fn select<'a>(&'a self, disks: &'a [DiskIo]) -> impl Iterator<Item = &'a DiskIo> {
+        disks.iter().filter(|disk| {
+            if self.globs.is_empty() {
+                disk.whole && !self.excluded.iter().any(|g| g.is_match(&disk.name))
+            } else {
+                self.globs.iter().any(|g| g.is_match(&disk.name))
+            }
+        })
+    }
+
+    /// Summed read and write rates.
+    fn total(&self, disks: &[DiskIo]) -> (u64, u64) {
+        self.select(disks)
+            .fold((0, 0), |(r, w), disk| (r + disk.read, w + disk.written))
+    }
+}
+
+#[typetag::serde]
+impl Config for DiskIoConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        tick::read_disk_io();
+        let receiver = tick::listen();
+        let selection = Selection::new(&self.devices);
+        let graphs = self.graph.clone().map(|g| {
+            let s = selection.clone();
+            let read = Graph::new(g.clone(), tick::listen(), move |msg| {
+                s.total(&msg.disk_io).0 as f32
+            });
+            let s = selection.clone();
+            let write = Graph::new(g, tick::listen(), move |msg| s.total(&msg.disk_io).1 as f32);
+            [read, write].map(|graph| graph.default_range(Some(0.0), None))
+        });
+
+        Box::new(DiskIoConsumer {
+            receiver,
+            selection,
+            graphs,
+            config: *self,
+        })
+    }
+}
+
+pub struct DiskIoConsumer {
+    receiver: watch::Receiver<tick::Message>,
+    selection: Selection,
+    /// Read and write history graphs.
+    graphs: Option<[Graph; 2]>,
+    config: DiskIoConfig,
+}
+
+impl DiskIoConsumer {
+    fn text(&self, value: u64) -> Text<'_> {
+        text(format!("{}/s", format_bytes(value))).color(self.config.colormap.map(value as f32))
+    }
+
+    fn with_graph<'a>(&'a self, value: u64, graph: Option<&'a Graph>) -> Element<'a, IcedMessage> {
+        match graph {
+            Some(graph) => row![self.text(value), graph.view(&self.config.colormap)]
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing / 2.0)
+                .into(),
+            None => self.text(value).into(),
+        }
+    }
+
+    fn device(&self, disk: &DiskIo) -> Element<'_, IcedMessage> {
+        row![
+            text(disk.name.clone()).color(self.config.label_color),
+            self.text(disk.read),
+            self.text(disk.written),
+        ]
+        .align_y(Vertical::Center)
+        .spacing(self.config.spacing / 2.0)
+        .into()
+    }
+}
+
+#[async_trait]
+impl Consumer for DiskIoConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let disks = &self.receiver.borrow().disk_io;
+
+        if self.config.per_device {
+            let devices = self.selection.select(disks).map(|disk| self.device(disk));
+            let graphs = self
+                .graphs
+                .iter()
+                .flatten()
+                .map(|g| g.view(&self.config.colormap));
+            return Row::with_children(devices.chain(graphs))
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing)
+                .into();
+        }
+
+        let (read, written) = self.selection.total(disks);
+        let [r, w] = match &self.graphs {
+            Some([r, w]) => [Some(r), Some(w)],
+            None => [None, None],
+        };
+        row![self.with_graph(read, r), self.with_graph(written, w)]
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing)
+            .into()
+    }
+}
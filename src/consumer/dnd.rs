@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element,
+    widget::{mouse_area, row, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, IcedMessage},
+    producer::dnd::{self, Daemon},
+};
+
+use super::Consumer;
+
+/// Shows whether a notification daemon is in do-not-disturb mode. Click to
+/// toggle it.
+#[derive(Deserialize, Serialize)]
+pub struct DndConfig {
+    /// Defaults to our own, which needs a notifications module to run it.
+    #[serde(default)]
+    pub daemon: Daemon,
+    #[serde(default = "default_text")]
+    pub text: String,
+    /// Color while paused.
+    pub color: Color,
+    /// Color while not paused.
+    #[serde(default = "default_label_color")]
+    pub label_color: Color,
+}
+
+fn default_text() -> String {
+    "dnd".into()
+}
+
+fn default_label_color() -> Color {
+    Color::from_rgb8(0xaa, 0xaa, 0xaa)
+}
+
+#[typetag::serde]
+impl Config for DndConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = dnd::listen(self.daemon);
+
+        Box::new(DndConsumer {
+            receiver,
+            config: *self,
+        })
+    }
+}
+
+pub struct DndConsumer {
+    receiver: watch::Receiver<dnd::Message>,
+    config: DndConfig,
+}
+
+#[async_trait]
+impl Consumer for DndConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let Some(paused) = self.receiver.borrow().paused else {
+            return row![].into();
+        };
+        let color = if paused {
+            self.config.color
+        } else {
+            self.config.label_color
+        };
+        let daemon = self.config.daemon;
+        mouse_area(text(self.config.text.clone()).color(color))
+            .on_press(IcedMessage::Action(Action::new(move || {
+                dnd::toggle(daemon)
+            })))
+            .into()
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use iced::{
+    Color, Element,
+    widget::{mouse_area, row, text},
+};
+use niri_ipc::{Action as NiriAction, LayoutSwitchTarget};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, IcedMessage},
+    producer::niri,
+};
+
+use super::Consumer;
+
+/// Shows niri's current keyboard layout. Click for the next layout; right
+/// click for the previous one.
+#[derive(Deserialize, Serialize)]
+pub struct KeyboardLayoutConfig {
+    /// Short names for layouts, by their XKB name, e.g.
+    /// `"English (US)" = "us"`. Layouts without one show in full.
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    pub color: Color,
+}
+
+#[typetag::serde]
+impl Config for KeyboardLayoutConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = niri::listen();
+
+        Box::new(KeyboardLayoutConsumer {
+            receiver,
+            config: *self,
+        })
+    }
+}
+
+pub struct KeyboardLayoutConsumer {
+    receiver: watch::Receiver<niri::Message>,
+    config: KeyboardLayoutConfig,
+}
+
+fn switch(layout: LayoutSwitchTarget) -> IcedMessage {
+    IcedMessage::Action(Action::new(move || {
+        niri::action(NiriAction::SwitchLayout { layout })
+    }))
+}
+
+#[async_trait]
+impl Consumer for KeyboardLayoutConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let Some(name) = msg
+            .keyboard_layouts
+            .as_ref()
+            .and_then(|layouts| layouts.names.get(usize::from(layouts.current_idx)))
+        else {
+            return row![].into();
+        };
+
+        let label = self.config.aliases.get(name).unwrap_or(name);
+        mouse_area(text(label.clone()).color(self.config.color))
+            .on_press(switch(LayoutSwitchTarget::Next))
+            .on_right_press(switch(LayoutSwitchTarget::Prev))
+            .into()
+    }
+}
@@ -1,28 +1,133 @@
 use async_trait::async_trait;
-use iced::{Element, widget::text};
+use iced::{
+    Element, Length, Theme,
+    alignment::Vertical,
+    border::Radius,
+    widget::{ProgressBar, row, text},
+};
 use serde::{Deserialize, Serialize};
 use tokio::sync::watch;
 
 use crate::{
     consumer::{Config, IcedMessage},
     producer::tick::{self},
-    util::{bytes::format_bytes, color::Colormap},
+    util::{
+        bytes::format_bytes,
+        color::Colormap,
+        graph::{Graph, GraphConfig},
+    },
 };
 
 use super::Consumer;
 
 #[derive(Deserialize, Serialize)]
 pub struct MemoryConfig {
+    /// Keyed by bytes in the `bytes` display, and by the fraction of the
+    /// stat's capacity (0.0 to 1.0) otherwise.
     pub colormap: Colormap,
+    #[serde(default)]
+    pub stat: MemoryStat,
+    #[serde(default)]
+    pub display: MemoryDisplay,
+    /// The size of the bar in the `bar` display.
+    #[serde(default = "default_width")]
+    pub width: f32,
+    #[serde(default = "default_height")]
+    pub height: f32,
+    #[serde(default = "default_spacing")]
+    pub spacing: f32,
+    /// If set, a history graph of the displayed value is drawn after it.
+    #[serde(default)]
+    pub graph: Option<GraphConfig>,
+}
+
+fn default_width() -> f32 {
+    40.0
+}
+
+fn default_height() -> f32 {
+    16.0
+}
+
+fn default_spacing() -> f32 {
+    4.0
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryStat {
+    /// RAM available for new allocations, out of total RAM.
+    #[default]
+    Available,
+    /// RAM in use, out of total RAM.
+    Used,
+    /// Swap in use, out of total swap.
+    Swap,
+    /// Uncompressed data stored in zram, out of the zram devices' size.
+    Zram,
+}
+
+impl MemoryStat {
+    /// The stat's value and the capacity it is a fraction of, in bytes.
+    fn read(self, memory: &tick::Memory) -> (u64, u64) {
+        match self {
+            MemoryStat::Available => (memory.available, memory.total),
+            MemoryStat::Used => (memory.used, memory.total),
+            MemoryStat::Swap => (memory.swap_used, memory.swap_total),
+            MemoryStat::Zram => memory
+                .zram
+                .as_ref()
+                .map_or((0, 0), |zram| (zram.data, zram.disksize)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryDisplay {
+    /// The stat in bytes, e.g. "3.20 G".
+    #[default]
+    Bytes,
+    /// The stat as a percentage of its capacity, e.g. "42%".
+    Percent,
+    /// A progress bar filled to the stat's fraction of its capacity.
+    Bar,
+}
+
+impl MemoryDisplay {
+    /// The value the colormap and graph are keyed by.
+    fn value(self, stat: MemoryStat, memory: &tick::Memory) -> f32 {
+        let (value, capacity) = stat.read(memory);
+        match self {
+            MemoryDisplay::Bytes => value as f32,
+            MemoryDisplay::Percent | MemoryDisplay::Bar if capacity == 0 => 0.0,
+            MemoryDisplay::Percent | MemoryDisplay::Bar => value as f32 / capacity as f32,
+        }
+    }
 }
 
 #[typetag::serde]
 impl Config for MemoryConfig {
     fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        if let MemoryStat::Zram = self.stat {
+            tick::read_zram();
+        }
         let receiver = tick::listen();
+        let (stat, display) = (self.stat, self.display);
+        let graph = self.graph.clone().map(|g| {
+            let max = match display {
+                MemoryDisplay::Bytes => None,
+                MemoryDisplay::Percent | MemoryDisplay::Bar => Some(1.0),
+            };
+            Graph::new(g, tick::listen(), move |msg| {
+                display.value(stat, &msg.memory)
+            })
+            .default_range(Some(0.0), max)
+        });
 
         Box::new(MemoryConsumer {
             receiver,
+            graph,
             config: *self,
         })
     }
@@ -30,9 +135,28 @@ impl Config for MemoryConfig {
 
 pub struct MemoryConsumer {
     receiver: watch::Receiver<tick::Message>,
+    graph: Option<Graph>,
     config: MemoryConfig,
 }
 
+impl MemoryConsumer {
+    fn bar(&self, value: f32) -> ProgressBar<'_, Theme> {
+        let color = self.config.colormap.map(value);
+        iced::widget::progress_bar(0.0..=1.0, value)
+            .length(Length::Fixed(self.config.width))
+            .girth(Length::Fixed(self.config.height))
+            .style(move |theme: &Theme| iced::widget::progress_bar::Style {
+                bar: color.into(),
+                border: iced::Border {
+                    color,
+                    width: 1.0,
+                    radius: Radius::new(0.0),
+                },
+                background: theme.palette().background.into(),
+            })
+    }
+}
+
 #[async_trait]
 impl Consumer for MemoryConsumer {
     async fn consume(&mut self) {
@@ -40,9 +164,22 @@ impl Consumer for MemoryConsumer {
     }
 
     fn render(&self, _: &str) -> Element<'_, IcedMessage> {
-        let mem = self.receiver.borrow().memory.available;
-        let t = format_bytes(mem);
-        let color = self.config.colormap.map(mem as f32);
-        text(t).color(color).into()
+        let memory = &self.receiver.borrow().memory;
+        let (stat, display) = (self.config.stat, self.config.display);
+        let value = display.value(stat, memory);
+        let color = self.config.colormap.map(value);
+
+        let t: Element<'_, IcedMessage> = match display {
+            MemoryDisplay::Bytes => text(format_bytes(stat.read(memory).0)).color(color).into(),
+            MemoryDisplay::Percent => text(format!("{:3.0}%", value * 100.0)).color(color).into(),
+            MemoryDisplay::Bar => self.bar(value).into(),
+        };
+        match &self.graph {
+            Some(graph) => row![t, graph.view(&self.config.colormap)]
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing)
+                .into(),
+            None => t,
+        }
     }
 }
@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element,
+    alignment::Vertical,
+    widget::{mouse_area, row, text},
+};
+use iced_core::text::Wrapping;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, IcedMessage, scroll_direction},
+    producer::{
+        mpris::{self, Command, PlaybackStatus, Player},
+        tick,
+    },
+    util::{glob::Glob, template},
+};
+
+use super::Consumer;
+
+/// Separates the end of scrolling text from its start coming around again.
+const MARQUEE_GAP: &str = "   ";
+
+#[derive(Deserialize, Serialize)]
+pub struct MprisConfig {
+    /// Players to prefer, by name or glob of their bus name without the
+    /// "org.mpris.MediaPlayer2." prefix (e.g. "spotify", "firefox*"). A
+    /// playing player is always shown over a paused one; otherwise, the
+    /// earliest listed wins, and unlisted players come last.
+    #[serde(default)]
+    pub players: Vec<String>,
+    /// Text to show, with placeholders `{artist}`, `{title}` and `{player}`.
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Longer text scrolls by, one character per second.
+    #[serde(default = "default_max_chars")]
+    pub max_chars: usize,
+    pub color: Color,
+    /// Color when paused or stopped.
+    pub paused_color: Color,
+    pub spacing: f32,
+}
+
+fn default_format() -> String {
+    "{artist} - {title}".into()
+}
+
+fn default_max_chars() -> usize {
+    40
+}
+
+#[typetag::serde]
+impl Config for MprisConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = mpris::listen();
+        let preferred = self.players.iter().map(|p| Glob::new(p)).collect();
+
+        Box::new(MprisConsumer {
+            receiver,
+            tick: tick::listen(),
+            preferred,
+            config: *self,
+        })
+    }
+}
+
+pub struct MprisConsumer {
+    receiver: watch::Receiver<mpris::Message>,
+    /// For scrolling the text.
+    tick: watch::Receiver<tick::Message>,
+    preferred: Vec<Glob>,
+    config: MprisConfig,
+}
+
+/// The player to show: playing before paused, then by preference.
+fn select<'a>(preferred: &[Glob], players: &'a [Player]) -> Option<&'a Player> {
+    players.iter().min_by_key(|player| {
+        let preference = preferred
+            .iter()
+            .position(|g| g.is_match(player.short_name()))
+            .unwrap_or(preferred.len());
+        (player.status != PlaybackStatus::Playing, preference)
+    })
+}
+
+/// A `width` character window onto `text`, which scrolls `step` characters
+/// through it, wrapping around. Text that fits is returned as is.
+fn marquee(text: &str, width: usize, step: usize) -> String {
+    let len = text.chars().count();
+    if len <= width {
+        return text.to_string();
+    }
+    let cycle = len + MARQUEE_GAP.chars().count();
+    text.chars()
+        .chain(MARQUEE_GAP.chars())
+        .cycle()
+        .skip(step % cycle)
+        .take(width)
+        .collect()
+}
+
+fn command(player: &Player, command: Command) -> IcedMessage {
+    let bus_name = player.bus_name.clone();
+    IcedMessage::Action(Action::new(move || {
+        mpris::command(bus_name.clone(), command)
+    }))
+}
+
+#[async_trait]
+impl Consumer for MprisConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let Some(player) = select(&self.preferred, &msg.players) else {
+            return row![].into();
+        };
+
+        let (icon, color) = match player.status {
+            PlaybackStatus::Playing => ("▶", self.config.color),
+            PlaybackStatus::Paused => ("⏸", self.config.paused_color),
+            PlaybackStatus::Stopped => ("■", self.config.paused_color),
+        };
+        let label = template::fill(&self.config.format, |name| match name {
+            "artist" => Some(player.artists.join(", ")),
+            "title" => Some(player.title.clone().unwrap_or_default()),
+            "player" => Some(
+                player
+                    .identity
+                    .clone()
+                    .unwrap_or_else(|| player.short_name().to_string()),
+            ),
+            _ => None,
+        });
+        let step = self.tick.borrow().time.timestamp().as_second() as usize;
+        let label = marquee(&label, self.config.max_chars, step);
+
+        let next = command(player, Command::Next);
+        let previous = command(player, Command::Previous);
+        mouse_area(
+            row![
+                text(icon).color(color),
+                text(label).wrapping(Wrapping::None).color(color),
+            ]
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing),
+        )
+        .on_press(command(player, Command::PlayPause))
+        .on_scroll(move |delta| match scroll_direction(delta) {
+            Some(d) if d > 0.0 => next.clone(),
+            Some(_) => previous.clone(),
+            None => IcedMessage::A,
+        })
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(name: &str, status: PlaybackStatus) -> Player {
+        Player {
+            bus_name: format!("org.mpris.MediaPlayer2.{name}"),
+            status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn selects_playing_then_preferred() {
+        let preferred = [Glob::new("spotify"), Glob::new("firefox*")];
+        let mut players = vec![
+            player("vlc", PlaybackStatus::Paused),
+            player("firefox.instance_1_84", PlaybackStatus::Paused),
+            player("spotify", PlaybackStatus::Stopped),
+        ];
+        let selected = |players: &[Player]| {
+            select(&preferred, players)
+                .unwrap()
+                .short_name()
+                .to_string()
+        };
+        assert_eq!(selected(&players), "spotify");
+
+        players[0].status = PlaybackStatus::Playing;
+        assert_eq!(selected(&players), "vlc");
+        assert!(select(&preferred, &[]).is_none());
+    }
+
+    #[test]
+    fn marquee_scrolls_and_wraps() {
+        assert_eq!(marquee("short", 10, 3), "short");
+        assert_eq!(marquee("abcdefgh", 4, 0), "abcd");
+        assert_eq!(marquee("abcdefgh", 4, 6), "gh  ");
+        assert_eq!(marquee("abcdefgh", 4, 9), "  ab");
+        // A whole cycle later, it's back where it started.
+        assert_eq!(marquee("abcdefgh", 4, 11), "abcd");
+    }
+
+    #[test]
+    fn marquee_counts_characters() {
+        assert_eq!(marquee("Sigur Rós – Hoppípolla", 9, 6), "Rós – Hop");
+    }
+}
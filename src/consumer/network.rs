@@ -1,33 +1,113 @@
 use async_trait::async_trait;
 use iced::{
-    Element,
+    Color, Element,
     alignment::Vertical,
-    widget::{Text, row, text},
+    widget::{Row, Text, row, text},
 };
 use serde::{Deserialize, Serialize};
 use tokio::sync::watch;
 
 use crate::{
     consumer::{Config, IcedMessage},
-    producer::tick::{self},
-    util::{bytes::format_bytes, color::Colormap},
+    producer::tick::{self, Interface},
+    util::{
+        bytes::format_bytes,
+        color::Colormap,
+        glob::Glob,
+        graph::{Graph, GraphConfig},
+    },
 };
 
 use super::Consumer;
 
 #[derive(Deserialize, Serialize)]
 pub struct NetworkConfig {
+    /// Keyed by bytes per second.
     pub colormap: Colormap,
     pub spacing: f32,
+    /// Interfaces to show, by name or glob (e.g. "wl*"). When empty, all
+    /// physical interfaces are shown.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// Show each selected interface separately, labeled with its name,
+    /// instead of summing them.
+    #[serde(default)]
+    pub per_interface: bool,
+    /// Show the first IP address of each interface (or of the first one that
+    /// has any, when summing).
+    #[serde(default)]
+    pub show_address: bool,
+    /// Hide interfaces whose link is down. When summing, the module is hidden
+    /// once every selected interface is down.
+    #[serde(default)]
+    pub hide_down: bool,
+    /// Color of interface names and addresses.
+    #[serde(default = "default_label_color")]
+    pub label_color: Color,
+    /// If set, interfaces whose link is down are shown in this color, names
+    /// and rates alike. When summing, it applies once every selected
+    /// interface is down.
+    #[serde(default)]
+    pub down_color: Option<Color>,
+    /// If set, history graphs of the summed received and transmitted rates
+    /// are drawn after their text.
+    #[serde(default)]
+    pub graph: Option<GraphConfig>,
+}
+
+fn default_label_color() -> Color {
+    Color::from_rgb8(0xaa, 0xaa, 0xaa)
+}
+
+/// Which interfaces a network module covers.
+#[derive(Clone)]
+struct Selection {
+    globs: Vec<Glob>,
+    hide_down: bool,
+}
+
+impl Selection {
+    fn select<'a>(&'a self, network: &'a tick::Network) -> impl Iterator<Item = &'a Interface> {
+        network.interfaces.iter().filter(|iface| {
+            let selected = if self.globs.is_empty() {
+                iface.physical
+            } else {
+                self.globs.iter().any(|g| g.is_match(&iface.name))
+            };
+            selected && (iface.up || !self.hide_down)
+        })
+    }
+
+    /// Summed received and transmitted rates.
+    fn total(&self, network: &tick::Network) -> (u64, u64) {
+        self.select(network).fold((0, 0), |(r, t), iface| {
+            (r + iface.received, t + iface.transmitted)
+        })
+    }
 }
 
 #[typetag::serde]
 impl Config for NetworkConfig {
     fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
         let receiver = tick::listen();
+        let selection = Selection {
+            globs: self.interfaces.iter().map(|i| Glob::new(i)).collect(),
+            hide_down: self.hide_down,
+        };
+        let graphs = self.graph.clone().map(|g| {
+            let s = selection.clone();
+            let rx = Graph::new(g.clone(), tick::listen(), move |msg| {
+                s.total(&msg.network).0 as f32
+            });
+            let s = selection.clone();
+            let tx = Graph::new(g, tick::listen(), move |msg| s.total(&msg.network).1 as f32);
+            [rx, tx].map(|graph| graph.default_range(Some(0.0), None))
+        });
 
         Box::new(NetworkConsumer {
             receiver,
+            selection,
+            graphs,
             config: *self,
         })
     }
@@ -35,12 +115,65 @@ impl Config for NetworkConfig {
 
 pub struct NetworkConsumer {
     receiver: watch::Receiver<tick::Message>,
+    selection: Selection,
+    /// Received and transmitted history graphs.
+    graphs: Option<[Graph; 2]>,
     config: NetworkConfig,
 }
 
 impl NetworkConsumer {
-    fn text(&self, value: u64) -> Text<'_> {
-        text(format_bytes(value)).color(self.config.colormap.map(value as f32))
+    /// `down_color` if set and the link is down, else `color`.
+    fn link_color(&self, up: bool, color: Color) -> Color {
+        match self.config.down_color {
+            Some(down) if !up => down,
+            _ => color,
+        }
+    }
+
+    fn text(&self, value: u64, up: bool) -> Text<'_> {
+        text(format!("{}/s", format_bytes(value)))
+            .color(self.link_color(up, self.config.colormap.map(value as f32)))
+    }
+
+    fn with_graph<'a>(
+        &'a self,
+        value: u64,
+        up: bool,
+        graph: Option<&'a Graph>,
+    ) -> Element<'a, IcedMessage> {
+        match graph {
+            Some(graph) => row![self.text(value, up), graph.view(&self.config.colormap)]
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing / 2.0)
+                .into(),
+            None => self.text(value, up).into(),
+        }
+    }
+
+    fn label(&self, iface: &Interface, name: bool) -> Option<Text<'_>> {
+        let address = self
+            .config
+            .show_address
+            .then(|| iface.addresses.first())
+            .flatten();
+        let label = match (name, address) {
+            (true, Some(addr)) => format!("{} {addr}", iface.name),
+            (true, None) => iface.name.clone(),
+            (false, Some(addr)) => addr.to_string(),
+            (false, None) => return None,
+        };
+        Some(text(label).color(self.link_color(iface.up, self.config.label_color)))
+    }
+
+    fn interface(&self, iface: &Interface) -> Element<'_, IcedMessage> {
+        row![
+            self.label(iface, true),
+            self.text(iface.received, iface.up),
+            self.text(iface.transmitted, iface.up),
+        ]
+        .align_y(Vertical::Center)
+        .spacing(self.config.spacing / 2.0)
+        .into()
     }
 }
 
@@ -52,9 +185,39 @@ impl Consumer for NetworkConsumer {
 
     fn render(&self, _: &str) -> Element<'_, IcedMessage> {
         let network = &self.receiver.borrow().network;
+        let selected: Vec<&Interface> = self.selection.select(network).collect();
+
+        if self.config.per_interface {
+            let interfaces = selected.iter().map(|iface| self.interface(iface));
+            let graphs = self
+                .graphs
+                .iter()
+                .flatten()
+                .map(|g| g.view(&self.config.colormap));
+            return Row::with_children(interfaces.chain(graphs))
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing)
+                .into();
+        }
+
+        if selected.is_empty() && self.config.hide_down {
+            return row![].into();
+        }
+
+        let (received, transmitted) = self.selection.total(network);
+        let up = selected.is_empty() || selected.iter().any(|iface| iface.up);
+        let [rx, tx] = match &self.graphs {
+            Some([rx, tx]) => [Some(rx), Some(tx)],
+            None => [None, None],
+        };
+        let address = selected
+            .iter()
+            .find(|iface| !iface.addresses.is_empty())
+            .and_then(|iface| self.label(iface, false));
         row![
-            self.text(network.bytes_received),
-            self.text(network.bytes_transmitted),
+            address,
+            self.with_graph(received, up, rx),
+            self.with_graph(transmitted, up, tx),
         ]
         .align_y(Vertical::Center)
         .spacing(self.config.spacing)
@@ -0,0 +1,162 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element,
+    alignment::Vertical,
+    widget::{Column, Row, button, mouse_area, row, text},
+};
+use iced_core::text::Wrapping;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, IcedMessage, PopupId},
+    producer::networkmanager::{self, ActiveConnection, Command},
+    util::template,
+};
+
+use super::Consumer;
+
+/// Shows NetworkManager's primary connection, and whether a VPN is up.
+/// Click for the saved connections, to switch to one or take it down.
+#[derive(Deserialize, Serialize)]
+pub struct NetworkManagerConfig {
+    /// Text for the primary connection, with placeholders `{name}`, `{type}`
+    /// (e.g. "wifi", "ethernet") and `{strength}` (e.g. "73%", for wifi).
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Shown while a VPN or WireGuard connection is active.
+    #[serde(default = "default_vpn_text")]
+    pub vpn_text: String,
+    /// Shown while there's no primary connection.
+    #[serde(default = "default_disconnected_text")]
+    pub disconnected_text: String,
+    pub color: Color,
+    /// Color of connections still activating, and of inactive ones in the
+    /// popup.
+    #[serde(default = "default_label_color")]
+    pub label_color: Color,
+    pub spacing: f32,
+}
+
+fn default_format() -> String {
+    "{name} {strength}".into()
+}
+
+fn default_vpn_text() -> String {
+    "vpn".into()
+}
+
+fn default_disconnected_text() -> String {
+    "offline".into()
+}
+
+fn default_label_color() -> Color {
+    Color::from_rgb8(0xaa, 0xaa, 0xaa)
+}
+
+#[typetag::serde]
+impl Config for NetworkManagerConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = networkmanager::listen();
+
+        Box::new(NetworkManagerConsumer {
+            receiver,
+            connections_popup: PopupId::unique(),
+            config: *self,
+        })
+    }
+}
+
+pub struct NetworkManagerConsumer {
+    receiver: watch::Receiver<networkmanager::Message>,
+    connections_popup: PopupId,
+    config: NetworkManagerConfig,
+}
+
+fn command(command: Command) -> IcedMessage {
+    IcedMessage::Action(Action::new(move || {
+        networkmanager::command(command.clone())
+    }))
+}
+
+impl NetworkManagerConsumer {
+    fn color(&self, connection: &ActiveConnection) -> Color {
+        if connection.activated {
+            self.config.color
+        } else {
+            self.config.label_color
+        }
+    }
+}
+
+#[async_trait]
+impl Consumer for NetworkManagerConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let Some(network) = &msg.network else {
+            return row![].into();
+        };
+
+        let primary = match network.primary() {
+            Some(primary) => {
+                let label = template::fill(&self.config.format, |name| match name {
+                    "name" => Some(primary.name.clone()),
+                    "type" => Some(primary.kind.name().to_string()),
+                    "strength" => Some(
+                        primary
+                            .strength
+                            .map(|s| format!("{s}%"))
+                            .unwrap_or_default(),
+                    ),
+                    _ => None,
+                });
+                text(label.trim().to_string())
+                    .wrapping(Wrapping::None)
+                    .color(self.color(primary))
+            }
+            None => text(self.config.disconnected_text.clone()).color(self.config.label_color),
+        };
+        // The VPN is usually not the primary connection, so it gets its own
+        // indicator.
+        let vpn = network
+            .vpn()
+            .filter(|vpn| !vpn.primary)
+            .map(|vpn| text(self.config.vpn_text.clone()).color(self.color(vpn)));
+        mouse_area(
+            Row::new()
+                .push(primary)
+                .extend(vpn.map(Element::from))
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing / 2.0),
+        )
+        .on_press(IcedMessage::TogglePopup(self.connections_popup))
+        .into()
+    }
+
+    fn popup(&self, id: PopupId, _: &str) -> Option<Element<'_, IcedMessage>> {
+        if id != self.connections_popup {
+            return None;
+        }
+        let msg = self.receiver.borrow();
+        let network = msg.network.as_ref()?;
+
+        let connections = network.saved.iter().map(|saved| {
+            let (color, toggle) = match network.activation(saved) {
+                Some(active) => (self.color(active), Command::Deactivate(active.path.clone())),
+                None => (
+                    self.config.label_color,
+                    Command::Activate(saved.path.clone()),
+                ),
+            };
+            button(text(format!("{} ({})", saved.name, saved.kind.name())).color(color))
+                .style(button::text)
+                .on_press(command(toggle))
+                .into()
+        });
+        Some(Column::with_children(connections).into())
+    }
+}
@@ -0,0 +1,339 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use iced::{
+    Color, Element, Length, Theme,
+    alignment::Vertical,
+    widget::{Column, Row, button, column, container, mouse_area, row, scrollable, text},
+};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    APP,
+    consumer::{Action, Config, IcedMessage, PopupId},
+    producer::{
+        notifications::{self, Command, Notification, Timeout},
+        tick,
+    },
+    util::notify::Urgency,
+};
+
+use super::Consumer;
+
+/// Makes rustybar the notification daemon. Shows how many notifications
+/// there are, with new ones as toasts beneath the bar. Click for the
+/// history; right click to toggle do-not-disturb.
+#[derive(Deserialize, Serialize)]
+pub struct NotificationsConfig {
+    pub color: Color,
+    /// Color of critical notifications.
+    pub urgent_color: Color,
+    /// Color of the count when there's nothing new, and of notification
+    /// details.
+    #[serde(default = "default_label_color")]
+    pub label_color: Color,
+    /// Seconds to show a toast for, when its app doesn't say. Critical
+    /// notifications stay until dismissed.
+    #[serde(default = "default_timeout")]
+    pub timeout: f32,
+    #[serde(default = "default_max_toasts")]
+    pub max_toasts: usize,
+    /// Width of toasts and of the history.
+    #[serde(default = "default_width")]
+    pub width: f32,
+    pub spacing: f32,
+}
+
+fn default_label_color() -> Color {
+    Color::from_rgb8(0xaa, 0xaa, 0xaa)
+}
+
+fn default_timeout() -> f32 {
+    5.0
+}
+
+fn default_max_toasts() -> usize {
+    3
+}
+
+fn default_width() -> f32 {
+    350.0
+}
+
+#[typetag::serde]
+impl Config for NotificationsConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = notifications::listen();
+        let timeout = Duration::try_from_secs_f32(self.timeout)
+            .inspect_err(|e| eprintln!("notifications: ignoring invalid timeout: {e}"))
+            .unwrap_or_else(|_| Duration::from_secs_f32(default_timeout()));
+
+        Box::new(NotificationsConsumer {
+            receiver,
+            tick: tick::listen(),
+            history_popup: PopupId::unique(),
+            timeout,
+            config: *self,
+        })
+    }
+}
+
+pub struct NotificationsConsumer {
+    receiver: watch::Receiver<notifications::Message>,
+    /// For expiring toasts.
+    tick: watch::Receiver<tick::Message>,
+    history_popup: PopupId,
+    /// The configured timeout.
+    timeout: Duration,
+    config: NotificationsConfig,
+}
+
+fn command(command: Command) -> IcedMessage {
+    IcedMessage::Action(Action::new(move || notifications::command(command.clone())))
+}
+
+/// The notifications to show as toasts, newest first.
+fn toasts(
+    notifications: &[Notification],
+    do_not_disturb: bool,
+    now: Timestamp,
+    default_timeout: Duration,
+    max: usize,
+) -> Vec<&Notification> {
+    notifications
+        .iter()
+        .rev()
+        .filter(|n| !do_not_disturb || n.urgency == Urgency::Critical)
+        .filter(|n| {
+            let timeout = match n.timeout {
+                Timeout::Never => return true,
+                Timeout::Default if n.urgency == Urgency::Critical => return true,
+                Timeout::Default => default_timeout,
+                Timeout::After(timeout) => timeout,
+            };
+            now.duration_since(n.received).unsigned_abs() < timeout
+        })
+        .take(max)
+        .collect()
+}
+
+/// How long ago something happened, briefly, e.g. "5m".
+fn age(elapsed: Duration) -> String {
+    match elapsed.as_secs() {
+        s if s < 60 => "now".into(),
+        s if s < 60 * 60 => format!("{}m", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h", s / (60 * 60)),
+        s => format!("{}d", s / (24 * 60 * 60)),
+    }
+}
+
+impl NotificationsConsumer {
+    fn now(&self) -> Timestamp {
+        self.tick.borrow().time.timestamp()
+    }
+
+    /// A notification's summary, body and actions. Clicking it invokes its
+    /// default action; right clicking dismisses it.
+    fn card(&self, notification: &Notification) -> Element<'_, IcedMessage> {
+        let color = match notification.urgency {
+            Urgency::Critical => self.config.urgent_color,
+            _ => self.config.color,
+        };
+        let age = age(self
+            .now()
+            .duration_since(notification.received)
+            .unsigned_abs());
+        let header = row![
+            text(notification.summary.clone())
+                .color(color)
+                .width(Length::Fill),
+            text(format!("{} {age}", notification.app_name)).color(self.config.label_color),
+        ]
+        .spacing(self.config.spacing);
+        let body = (!notification.body.is_empty()).then(|| text(notification.body.clone()));
+        let id = notification.id;
+        let actions = notification
+            .actions
+            .iter()
+            .filter(|(key, _)| key != "default")
+            .map(|(key, label)| {
+                button(text(label.clone()))
+                    .style(button::secondary)
+                    .on_press(command(Command::Invoke(id, key.clone())))
+                    .into()
+            });
+        let actions = Row::with_children(actions).spacing(self.config.spacing / 2.0);
+
+        let on_press = if notification.has_default_action() {
+            command(Command::Invoke(id, "default".into()))
+        } else {
+            IcedMessage::A
+        };
+        mouse_area(
+            column![header, body, actions]
+                .spacing(4)
+                .width(Length::Fixed(self.config.width)),
+        )
+        .on_press(on_press)
+        .on_right_press(command(Command::Dismiss(id)))
+        .into()
+    }
+}
+
+#[async_trait]
+impl Consumer for NotificationsConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let count = msg.notifications.len();
+        let color = if msg
+            .notifications
+            .iter()
+            .any(|n| n.urgency == Urgency::Critical)
+        {
+            self.config.urgent_color
+        } else if count > 0 && !msg.do_not_disturb {
+            self.config.color
+        } else {
+            self.config.label_color
+        };
+        let dnd = msg
+            .do_not_disturb
+            .then(|| text("dnd").color(self.config.label_color));
+
+        mouse_area(
+            row![dnd, text(count).color(color)]
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing / 2.0),
+        )
+        .on_press(IcedMessage::TogglePopup(self.history_popup))
+        .on_right_press(command(Command::ToggleDoNotDisturb))
+        .into()
+    }
+
+    fn popup(&self, id: PopupId, _: &str) -> Option<Element<'_, IcedMessage>> {
+        if id != self.history_popup {
+            return None;
+        }
+        let msg = self.receiver.borrow();
+        let dnd_label = if msg.do_not_disturb {
+            "Do not disturb: on"
+        } else {
+            "Do not disturb: off"
+        };
+        let controls = row![
+            button(text(dnd_label))
+                .style(button::text)
+                .on_press(command(Command::ToggleDoNotDisturb)),
+            button(text("Clear all"))
+                .style(button::text)
+                .on_press(command(Command::DismissAll)),
+        ];
+        let history = msg.notifications.iter().rev().map(|n| self.card(n));
+        let history = Column::with_children(history).spacing(self.config.spacing);
+        Some(column![controls, scrollable(history)].spacing(8).into())
+    }
+
+    fn toasts(&self, _: &str) -> Option<Element<'_, IcedMessage>> {
+        let msg = self.receiver.borrow();
+        let toasts = toasts(
+            &msg.notifications,
+            msg.do_not_disturb,
+            self.now(),
+            self.timeout,
+            self.config.max_toasts,
+        );
+        if toasts.is_empty() {
+            return None;
+        }
+        let toasts = toasts.into_iter().map(|n| {
+            container(self.card(n))
+                .padding(8)
+                .style(|theme: &Theme| {
+                    container::bordered_box(theme).background(APP.config.background)
+                })
+                .into()
+        });
+        Some(Column::with_children(toasts).spacing(4).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(id: u32, urgency: Urgency, timeout: Timeout, received: i64) -> Notification {
+        Notification {
+            id,
+            app_name: "app".into(),
+            summary: format!("#{id}"),
+            body: String::new(),
+            urgency,
+            actions: Vec::new(),
+            timeout,
+            received: Timestamp::from_second(received).unwrap(),
+        }
+    }
+
+    fn ids(toasts: Vec<&Notification>) -> Vec<u32> {
+        toasts.into_iter().map(|n| n.id).collect()
+    }
+
+    #[test]
+    fn toasts_expire() {
+        let notifications = [
+            notification(1, Urgency::Critical, Timeout::Default, 0),
+            notification(2, Urgency::Normal, Timeout::Never, 0),
+            notification(3, Urgency::Normal, Timeout::Default, 90),
+            notification(4, Urgency::Low, Timeout::After(Duration::from_secs(30)), 80),
+        ];
+        let now = Timestamp::from_second(100).unwrap();
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            ids(toasts(&notifications, false, now, timeout, 10)),
+            [4, 2, 1]
+        );
+        assert_eq!(
+            ids(toasts(
+                &notifications,
+                false,
+                now,
+                Duration::from_secs(20),
+                2
+            )),
+            [4, 3]
+        );
+    }
+
+    #[test]
+    fn do_not_disturb_lets_critical_through() {
+        let notifications = [
+            notification(1, Urgency::Critical, Timeout::Default, 0),
+            notification(2, Urgency::Normal, Timeout::Never, 0),
+        ];
+        let now = Timestamp::from_second(100).unwrap();
+        assert_eq!(
+            ids(toasts(
+                &notifications,
+                true,
+                now,
+                Duration::from_secs(5),
+                10
+            )),
+            [1]
+        );
+    }
+
+    #[test]
+    fn ages_briefly() {
+        assert_eq!(age(Duration::from_secs(59)), "now");
+        assert_eq!(age(Duration::from_secs(5 * 60 + 10)), "5m");
+        assert_eq!(age(Duration::from_secs(3 * 60 * 60)), "3h");
+        assert_eq!(age(Duration::from_secs(50 * 60 * 60)), "2d");
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use iced::{Element, widget::text};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Config, IcedMessage},
+    producer::tick::{self, SystemInfo},
+    util::{color::Colormap, template},
+};
+
+use super::Consumer;
+
+#[derive(Deserialize, Serialize)]
+pub struct SystemConfig {
+    /// Text to show, with placeholders `{load1}`, `{load5}`, `{load15}`,
+    /// `{uptime}`, `{processes}` and `{running}` (runnable threads).
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Keyed by the 1 minute load average per CPU, so 1.0 means every CPU
+    /// is busy.
+    pub colormap: Colormap,
+}
+
+fn default_format() -> String {
+    "{load1} {load5} {load15}".into()
+}
+
+#[typetag::serde]
+impl Config for SystemConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = tick::listen();
+        if self.format.contains("{processes}") {
+            tick::count_processes();
+        }
+
+        Box::new(SystemConsumer {
+            receiver,
+            config: *self,
+        })
+    }
+}
+
+pub struct SystemConsumer {
+    receiver: watch::Receiver<tick::Message>,
+    config: SystemConfig,
+}
+
+fn placeholder(system: &SystemInfo, name: &str) -> Option<String> {
+    Some(match name {
+        "load1" => format!("{:.2}", system.load[0]),
+        "load5" => format!("{:.2}", system.load[1]),
+        "load15" => format!("{:.2}", system.load[2]),
+        "uptime" => format_uptime(system.uptime),
+        "processes" => system.processes.to_string(),
+        "running" => system.running_threads.to_string(),
+        _ => return None,
+    })
+}
+
+/// Formats an uptime as days, hours and minutes, e.g. "3d 4:05", leaving out
+/// the days when there are none.
+fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{days}d {hours}:{minutes:02}")
+    } else {
+        format!("{hours}:{minutes:02}")
+    }
+}
+
+#[async_trait]
+impl Consumer for SystemConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let system = &self.receiver.borrow().system;
+        let t = template::fill(&self.config.format, |name| placeholder(system, name));
+        let load = system.load[0] as f32 / system.cpus.max(1) as f32;
+        text(t).color(self.config.colormap.map(load)).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uptime() {
+        assert_eq!(format_uptime(Duration::from_secs(59)), "0:00");
+        assert_eq!(
+            format_uptime(Duration::from_secs(3 * 3600 + 5 * 60)),
+            "3:05"
+        );
+        assert_eq!(
+            format_uptime(Duration::from_secs(2 * 86400 + 23 * 3600 + 60)),
+            "2d 23:01"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element,
+    alignment::Vertical,
+    widget::{Row, image, mouse_area, row, svg, text},
+};
+use iced_core::text::Wrapping;
+use niri_ipc::{Action as NiriAction, Window};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, Consumer, IcedMessage},
+    producer::niri,
+    util::{icon, overflow_row::OverflowRow},
+};
+
+/// Lists the windows on the output's active workspace, in the order they're
+/// laid out, with app icons and titles. Click a window to focus it.
+#[derive(Deserialize, Serialize)]
+pub struct TaskbarConfig {
+    pub color: Color,
+    pub focused_color: Color,
+    pub urgent_color: Color,
+    /// Longer titles are cut short with "…". 0 shows icons only.
+    #[serde(default = "default_max_title_chars")]
+    pub max_title_chars: usize,
+    #[serde(default = "default_icon_size")]
+    pub icon_size: f32,
+    pub spacing: f32,
+    /// Max width as a fraction of the bar region's available width
+    /// (1.0 = the full region). When exceeded, the list is clipped and
+    /// scrolled to keep the focused window centered.
+    #[serde(default = "default_max_width")]
+    pub max_width: f32,
+}
+
+fn default_max_title_chars() -> usize {
+    20
+}
+
+fn default_icon_size() -> f32 {
+    16.0
+}
+
+fn default_max_width() -> f32 {
+    1.0
+}
+
+#[typetag::serde]
+impl Config for TaskbarConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = niri::listen();
+
+        Box::new(TaskbarConsumer {
+            receiver,
+            config: *self,
+        })
+    }
+}
+
+pub struct TaskbarConsumer {
+    receiver: watch::Receiver<niri::Message>,
+    config: TaskbarConfig,
+}
+
+fn focus(id: u64) -> IcedMessage {
+    IcedMessage::Action(Action::new(move || {
+        niri::action(NiriAction::FocusWindow { id })
+    }))
+}
+
+/// `title` cut to `max` characters, ending in "…" if it was longer.
+fn truncate(title: &str, max: usize) -> String {
+    if title.chars().count() <= max {
+        return title.to_string();
+    }
+    let mut short: String = title.chars().take(max.saturating_sub(1)).collect();
+    short.push('…');
+    short
+}
+
+impl TaskbarConsumer {
+    /// The window's app icon, once it's been found.
+    fn icon(&self, window: &Window) -> Option<Element<'_, IcedMessage>> {
+        let path = icon::app_icon(window.app_id.as_deref()?)?;
+        let size = self.config.icon_size;
+        Some(if path.extension().is_some_and(|e| e == "svg") {
+            svg(svg::Handle::from_path(path))
+                .width(size)
+                .height(size)
+                .into()
+        } else {
+            image(image::Handle::from_path(path))
+                .width(size)
+                .height(size)
+                .into()
+        })
+    }
+}
+
+#[async_trait]
+impl Consumer for TaskbarConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, output: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let Some(output) = msg.outputs.get(output) else {
+            return row![].into();
+        };
+        let windows = &output.workspace_windows;
+        if windows.is_empty() {
+            return row![].into();
+        }
+
+        let focused = windows.iter().position(|w| w.is_focused);
+        let urgent = windows
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.is_urgent)
+            .map(|(i, _)| i)
+            .collect();
+
+        let separator = || text("…").color(self.config.color).into();
+
+        let entries = windows.iter().map(|window| {
+            let color = if window.is_urgent {
+                self.config.urgent_color
+            } else if window.is_focused {
+                self.config.focused_color
+            } else {
+                self.config.color
+            };
+            let title = window.title.as_deref().unwrap_or_default();
+            let label = (self.config.max_title_chars > 0).then(|| {
+                text(truncate(title, self.config.max_title_chars))
+                    .wrapping(Wrapping::None)
+                    .color(color)
+            });
+            let entry = Row::new()
+                .extend(self.icon(window))
+                .extend(label.map(Element::from))
+                .align_y(Vertical::Center)
+                .spacing(self.config.spacing / 3.0);
+            mouse_area(entry).on_press(focus(window.id)).into()
+        });
+
+        OverflowRow::new(
+            entries,
+            [separator(), separator()],
+            focused,
+            urgent,
+            self.config.max_width,
+            self.config.spacing,
+        )
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncates_long_titles() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("exactly10!", 10), "exactly10!");
+        assert_eq!(truncate("a rather long title", 10), "a rather …");
+        assert_eq!(truncate("ünïcödé", 4), "ünï…");
+    }
+}
@@ -1,28 +1,150 @@
 use async_trait::async_trait;
-use iced::{Element, widget::text};
+use iced::{
+    Color, Element,
+    alignment::Vertical,
+    widget::{Row, Text, row, text},
+};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tokio::sync::watch;
 
 use crate::{
     consumer::{Config, IcedMessage},
-    producer::tick::{self},
-    util::color::Colormap,
+    producer::tick::{self, Sensor},
+    util::{
+        color::Colormap,
+        graph::{Graph, GraphConfig},
+    },
 };
 
 use super::Consumer;
 
 #[derive(Deserialize, Serialize)]
 pub struct TempConfig {
+    /// Keyed by degrees Celsius, whatever the displayed unit.
     pub colormap: Colormap,
+    /// Regex matched against sensor labels, e.g. "^(k10temp|coretemp)". When
+    /// unset, every sensor is used.
+    #[serde(default)]
+    pub sensors: Option<String>,
+    #[serde(default)]
+    pub aggregate: TempAggregate,
+    #[serde(default)]
+    pub unit: TempUnit,
+    /// Color used instead of the colormap when a sensor reaches the critical
+    /// temperature its hardware reports.
+    #[serde(default)]
+    pub critical_color: Option<Color>,
+    /// Color of sensor labels, shown when each sensor is displayed.
+    #[serde(default = "default_label_color")]
+    pub label_color: Color,
+    #[serde(default = "default_spacing")]
+    pub spacing: f32,
+    /// If set, a history graph of the aggregated temperature (the maximum,
+    /// when showing each sensor) is drawn after the text.
+    #[serde(default)]
+    pub graph: Option<GraphConfig>,
+}
+
+fn default_label_color() -> Color {
+    Color::from_rgb8(0xaa, 0xaa, 0xaa)
+}
+
+fn default_spacing() -> f32 {
+    10.0
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TempAggregate {
+    /// The hottest selected sensor.
+    #[default]
+    Max,
+    /// The mean of the selected sensors.
+    Avg,
+    /// Each selected sensor, labeled.
+    Each,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum TempUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TempUnit {
+    fn format(self, celsius: f32) -> String {
+        match self {
+            TempUnit::Celsius => format!("{celsius:3.0} °C"),
+            TempUnit::Fahrenheit => format!("{:3.0} °F", celsius * 9.0 / 5.0 + 32.0),
+        }
+    }
+}
+
+/// Which sensors a temperature module covers, and how it combines them.
+#[derive(Clone)]
+struct Selection {
+    sensors: Option<Regex>,
+    aggregate: TempAggregate,
+}
+
+impl Selection {
+    fn select<'a>(&'a self, temp: &'a tick::Temperature) -> impl Iterator<Item = &'a Sensor> {
+        temp.sensors
+            .iter()
+            .filter(|s| self.sensors.as_ref().is_none_or(|r| r.is_match(&s.label)))
+    }
+
+    /// The selected sensors combined into one, labeled with the first
+    /// sensor's label, and critical if any of them is. `None` if no sensor is
+    /// selected.
+    fn combine(&self, temp: &tick::Temperature) -> Option<Sensor> {
+        let selected: Vec<&Sensor> = self.select(temp).collect();
+        let first = selected.first()?;
+        let temp = match self.aggregate {
+            TempAggregate::Avg => {
+                selected.iter().map(|s| s.temp).sum::<f32>() / selected.len() as f32
+            }
+            TempAggregate::Max | TempAggregate::Each => {
+                selected.iter().map(|s| s.temp).fold(f32::MIN, f32::max)
+            }
+        };
+        let critical = selected.iter().any(|s| s.is_critical());
+        Some(Sensor {
+            label: first.label.clone(),
+            temp,
+            // Any threshold at or below `temp` keeps `is_critical` right.
+            critical: critical.then_some(temp),
+        })
+    }
 }
 
 #[typetag::serde]
 impl Config for TempConfig {
     fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
         let receiver = tick::listen();
+        let sensors = self.sensors.as_deref().and_then(|r| {
+            Regex::new(r)
+                .inspect_err(|e| eprintln!("temp: ignoring invalid sensor regex: {e}"))
+                .ok()
+        });
+        let selection = Selection {
+            sensors,
+            aggregate: self.aggregate,
+        };
+        let graph = self.graph.clone().map(|g| {
+            let s = selection.clone();
+            Graph::new(g, tick::listen(), move |msg| {
+                s.combine(&msg.temp).map(|s| s.temp).unwrap_or_default()
+            })
+        });
 
         Box::new(TempConsumer {
             receiver,
+            selection,
+            graph,
             config: *self,
         })
     }
@@ -30,9 +152,21 @@ impl Config for TempConfig {
 
 pub struct TempConsumer {
     receiver: watch::Receiver<tick::Message>,
+    selection: Selection,
+    graph: Option<Graph>,
     config: TempConfig,
 }
 
+impl TempConsumer {
+    fn text(&self, sensor: &Sensor) -> Text<'_> {
+        let color = match self.config.critical_color {
+            Some(color) if sensor.is_critical() => color,
+            _ => self.config.colormap.map(sensor.temp),
+        };
+        text(self.config.unit.format(sensor.temp)).color(color)
+    }
+}
+
 #[async_trait]
 impl Consumer for TempConsumer {
     async fn consume(&mut self) {
@@ -40,9 +174,106 @@ impl Consumer for TempConsumer {
     }
 
     fn render(&self, _: &str) -> Element<'_, IcedMessage> {
-        let max = self.receiver.borrow().temp.max;
-        let t = format!("{max:3.0} °C");
-        let color = self.config.colormap.map(max);
-        text(t).color(color).into()
+        let temp = &self.receiver.borrow().temp;
+        // Hide the module entirely on machines without matching sensors.
+        let Some(combined) = self.selection.combine(temp) else {
+            return row![].into();
+        };
+
+        let graph = self.graph.as_ref().map(|g| g.view(&self.config.colormap));
+        let temps: Vec<Element<'_, IcedMessage>> = match self.config.aggregate {
+            TempAggregate::Each => self
+                .selection
+                .select(temp)
+                .map(|sensor| {
+                    row![
+                        text(sensor.label.clone()).color(self.config.label_color),
+                        self.text(sensor),
+                    ]
+                    .spacing(self.config.spacing / 2.0)
+                    .into()
+                })
+                .collect(),
+            TempAggregate::Max | TempAggregate::Avg => vec![self.text(&combined).into()],
+        };
+        Row::with_children(temps.into_iter().chain(graph))
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensor(label: &str, temp: f32, critical: Option<f32>) -> Sensor {
+        Sensor {
+            label: label.into(),
+            temp,
+            critical,
+        }
+    }
+
+    fn temperature() -> tick::Temperature {
+        tick::Temperature {
+            sensors: vec![
+                sensor("k10temp Tctl", 60.0, None),
+                sensor("nvme Composite", 40.0, Some(80.0)),
+                sensor("amdgpu edge", 90.0, Some(85.0)),
+            ],
+        }
+    }
+
+    fn selection(sensors: Option<&str>, aggregate: TempAggregate) -> Selection {
+        Selection {
+            sensors: sensors.map(|r| Regex::new(r).unwrap()),
+            aggregate,
+        }
+    }
+
+    #[test]
+    fn max_and_avg_over_selected_sensors() {
+        let temp = temperature();
+        let max = selection(Some("^(k10temp|nvme)"), TempAggregate::Max)
+            .combine(&temp)
+            .unwrap();
+        assert_eq!(max.temp, 60.0);
+        assert!(!max.is_critical());
+
+        let avg = selection(Some("^(k10temp|nvme)"), TempAggregate::Avg)
+            .combine(&temp)
+            .unwrap();
+        assert_eq!(avg.temp, 50.0);
+    }
+
+    #[test]
+    fn critical_if_any_sensor_is() {
+        // The average is below every threshold, but the GPU is past its own.
+        let avg = selection(None, TempAggregate::Avg)
+            .combine(&temperature())
+            .unwrap();
+        assert!(avg.temp < 80.0);
+        assert!(avg.is_critical());
+    }
+
+    #[test]
+    fn nothing_selected() {
+        assert!(
+            selection(Some("^acpitz"), TempAggregate::Max)
+                .combine(&temperature())
+                .is_none()
+        );
+        assert!(
+            selection(None, TempAggregate::Max)
+                .combine(&tick::Temperature::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn fahrenheit() {
+        assert_eq!(TempUnit::Fahrenheit.format(100.0), "212 °F");
+        assert_eq!(TempUnit::Celsius.format(5.0), "  5 °C");
     }
 }
@@ -0,0 +1,198 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use iced::{
+    Color, Element, Length,
+    alignment::Vertical,
+    widget::{Column, Row, button, image, mouse_area, rule, scrollable, svg, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, IcedMessage, PopupId, scroll_direction},
+    producer::tray::{self, Command, Icon, Item, Menu, MenuItem, Status},
+};
+
+use super::Consumer;
+
+#[derive(Deserialize, Serialize)]
+pub struct TrayConfig {
+    #[serde(default = "default_icon_size")]
+    pub icon_size: f32,
+    pub spacing: f32,
+    /// Color of menu entries, and of the title shown for items without an
+    /// icon.
+    pub color: Color,
+    /// Color of menu entries that can't be clicked.
+    #[serde(default = "default_disabled_color")]
+    pub disabled_color: Color,
+    /// Show items that mark themselves as passive, which usually means
+    /// there's nothing to see.
+    #[serde(default)]
+    pub show_passive: bool,
+}
+
+fn default_icon_size() -> f32 {
+    18.0
+}
+
+fn default_disabled_color() -> Color {
+    Color::from_rgb8(0x66, 0x66, 0x66)
+}
+
+#[typetag::serde]
+impl Config for TrayConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = tray::listen();
+
+        Box::new(TrayConsumer {
+            receiver,
+            menu_popups: Mutex::default(),
+            config: *self,
+        })
+    }
+}
+
+pub struct TrayConsumer {
+    receiver: watch::Receiver<tray::Message>,
+    /// The popup for each item's menu, by item address.
+    menu_popups: Mutex<HashMap<String, PopupId>>,
+    config: TrayConfig,
+}
+
+fn command(address: &str, command: Command) -> IcedMessage {
+    let address = address.to_string();
+    IcedMessage::Action(Action::new(move || {
+        tray::command(address.clone(), command.clone())
+    }))
+}
+
+impl TrayConsumer {
+    fn menu_popup(&self, address: &str) -> PopupId {
+        *self
+            .menu_popups
+            .lock()
+            .unwrap()
+            .entry(address.to_string())
+            .or_insert_with(PopupId::unique)
+    }
+
+    fn icon(&self, item: &Item) -> Element<'_, IcedMessage> {
+        let size = Length::Fixed(self.config.icon_size);
+        match &item.icon {
+            Some(Icon::Image(handle)) => image(handle.clone()).width(size).height(size).into(),
+            Some(Icon::Svg(handle)) => svg(handle.clone()).width(size).height(size).into(),
+            None => text(item.title.clone()).color(self.config.color).into(),
+        }
+    }
+
+    fn item(&self, item: &Item) -> Element<'_, IcedMessage> {
+        let address = item.address.as_str();
+        // Opening the menu tells the item first, so it can fill it in.
+        let open_menu = item.menu.as_ref().map(|menu| {
+            IcedMessage::Batch(vec![
+                command(address, Command::MenuOpened(menu.path.clone())),
+                IcedMessage::TogglePopup(self.menu_popup(address)),
+            ])
+        });
+        let primary = match &open_menu {
+            Some(open_menu) if item.item_is_menu => open_menu.clone(),
+            _ => command(address, Command::Activate),
+        };
+        let secondary = open_menu.unwrap_or_else(|| command(address, Command::ContextMenu));
+
+        let scroll_address = item.address.clone();
+        mouse_area(self.icon(item))
+            .on_press(primary)
+            .on_right_press(secondary)
+            .on_middle_press(command(address, Command::SecondaryActivate))
+            .on_scroll(move |delta| match scroll_direction(delta) {
+                Some(direction) => command(&scroll_address, Command::Scroll(direction as i32)),
+                None => IcedMessage::A,
+            })
+            .into()
+    }
+
+    /// The entries of a menu, with submenus inline and indented beneath
+    /// their parent.
+    fn menu_entries<'a>(
+        &self,
+        address: &str,
+        menu: &Menu,
+        items: &[MenuItem],
+        depth: u16,
+        entries: &mut Vec<Element<'a, IcedMessage>>,
+    ) {
+        for item in items.iter().filter(|i| i.visible) {
+            if item.separator {
+                entries.push(rule::horizontal(1).into());
+                continue;
+            }
+            let mark = match item.checked {
+                Some(true) => "✓ ",
+                Some(false) => "  ",
+                None => "",
+            };
+            let color = if item.enabled {
+                self.config.color
+            } else {
+                self.config.disabled_color
+            };
+            let clickable = item.enabled && item.children.is_empty();
+            let entry = button(text(format!("{mark}{}", item.label)).color(color))
+                .style(button::text)
+                .padding([2, 8 + 16 * depth])
+                .on_press_maybe(clickable.then(|| {
+                    IcedMessage::Batch(vec![
+                        command(address, Command::MenuClicked(menu.path.clone(), item.id)),
+                        IcedMessage::ClosePopup,
+                    ])
+                }));
+            entries.push(entry.into());
+            self.menu_entries(address, menu, &item.children, depth + 1, entries);
+        }
+    }
+}
+
+#[async_trait]
+impl Consumer for TrayConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        // Forget the popups of items that have gone away.
+        self.menu_popups
+            .lock()
+            .unwrap()
+            .retain(|address, _| msg.items.iter().any(|item| item.address == *address));
+        let items = msg
+            .items
+            .iter()
+            .filter(|item| self.config.show_passive || item.status != Status::Passive)
+            .map(|item| self.item(item));
+        Row::with_children(items)
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing)
+            .into()
+    }
+
+    fn popup(&self, id: PopupId, _: &str) -> Option<Element<'_, IcedMessage>> {
+        let address = self
+            .menu_popups
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, popup)| **popup == id)
+            .map(|(address, _)| address.clone())?;
+        let msg = self.receiver.borrow();
+        let item = msg.items.iter().find(|item| item.address == address)?;
+        let menu = item.menu.as_ref()?;
+
+        let mut entries = Vec::new();
+        self.menu_entries(&address, menu, &menu.items, 0, &mut entries);
+        Some(scrollable(Column::with_children(entries)).into())
+    }
+}
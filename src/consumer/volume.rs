@@ -0,0 +1,164 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element, Length, Theme,
+    alignment::Vertical,
+    border::Radius,
+    widget::{Column, ProgressBar, button, mouse_area, row, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Action, Config, IcedMessage, PopupId, scroll_direction},
+    producer::audio::{self, Command},
+};
+
+use super::Consumer;
+
+#[derive(Deserialize, Serialize)]
+pub struct VolumeConfig {
+    pub color: Color,
+    /// Color of the bar and microphone indicator when muted.
+    pub muted_color: Color,
+    pub width: f32,
+    pub height: f32,
+    pub spacing: f32,
+    /// How much one scroll step changes the volume, where 1.0 is 100%.
+    #[serde(default = "default_step")]
+    pub step: f32,
+    /// The highest scrolling raises the volume to, where 1.0 is 100%.
+    #[serde(default = "default_max_volume")]
+    pub max_volume: f32,
+    #[serde(default)]
+    pub show_percent: bool,
+    /// Show whether the microphone is muted. Clicking it toggles that.
+    #[serde(default)]
+    pub show_mic: bool,
+}
+
+fn default_step() -> f32 {
+    0.05
+}
+
+fn default_max_volume() -> f32 {
+    1.0
+}
+
+#[typetag::serde]
+impl Config for VolumeConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = audio::listen();
+
+        Box::new(VolumeConsumer {
+            receiver,
+            sinks_popup: PopupId::unique(),
+            config: *self,
+        })
+    }
+}
+
+pub struct VolumeConsumer {
+    receiver: watch::Receiver<audio::Message>,
+    /// Lists sinks to choose the default from.
+    sinks_popup: PopupId,
+    config: VolumeConfig,
+}
+
+fn command(command: Command) -> IcedMessage {
+    IcedMessage::Action(Action::new(move || audio::command(command.clone())))
+}
+
+impl VolumeConsumer {
+    fn bar(&self, volume: f32, color: Color) -> ProgressBar<'_, Theme> {
+        iced::widget::progress_bar(0.0..=1.0, volume)
+            .length(Length::Fixed(self.config.width))
+            .girth(Length::Fixed(self.config.height))
+            .style(move |theme: &Theme| iced::widget::progress_bar::Style {
+                bar: color.into(),
+                border: iced::Border {
+                    color,
+                    width: 1.0,
+                    radius: Radius::new(0.0),
+                },
+                background: theme.palette().background.into(),
+            })
+    }
+
+    fn color(&self, muted: bool) -> Color {
+        if muted {
+            self.config.muted_color
+        } else {
+            self.config.color
+        }
+    }
+}
+
+#[async_trait]
+impl Consumer for VolumeConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let Some(audio) = &msg.audio else {
+            return row![].into();
+        };
+
+        let sink = audio.default_sink().map(|sink| {
+            let color = self.color(sink.muted);
+            let percent = self
+                .config
+                .show_percent
+                .then(|| text(format!("{:.0}%", sink.volume * 100.0)).color(color));
+            let (step, max) = (self.config.step, self.config.max_volume);
+            mouse_area(
+                row![self.bar(sink.volume, color), percent]
+                    .align_y(Vertical::Center)
+                    .spacing(self.config.spacing / 2.0),
+            )
+            .on_press(command(Command::ToggleMute))
+            .on_right_press(IcedMessage::TogglePopup(self.sinks_popup))
+            .on_scroll(move |delta| match scroll_direction(delta) {
+                Some(direction) => command(Command::ChangeVolume {
+                    delta: direction * step,
+                    max,
+                }),
+                None => IcedMessage::A,
+            })
+        });
+        let mic = audio
+            .mic_muted
+            .filter(|_| self.config.show_mic)
+            .map(|muted| {
+                mouse_area(text("mic").color(self.color(muted)))
+                    .on_press(command(Command::ToggleMicMute))
+            });
+
+        row![sink, mic]
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing)
+            .into()
+    }
+
+    fn popup(&self, id: PopupId, _: &str) -> Option<Element<'_, IcedMessage>> {
+        if id != self.sinks_popup {
+            return None;
+        }
+        let msg = self.receiver.borrow();
+        let audio = msg.audio.as_ref()?;
+        let sinks = audio.sinks.iter().map(|sink| {
+            let is_default = audio.default_sink.as_ref() == Some(&sink.name);
+            let color = if is_default {
+                self.config.color
+            } else {
+                self.config.muted_color
+            };
+            button(text(sink.description.clone()).color(color))
+                .style(button::text)
+                .on_press(command(Command::SetDefaultSink(sink.name.clone())))
+                .into()
+        });
+        Some(Column::with_children(sinks).into())
+    }
+}
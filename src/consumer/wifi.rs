@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use iced::{
+    Color, Element, Length, Theme,
+    alignment::Vertical,
+    border::Radius,
+    widget::{ProgressBar, Row, row, text},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{
+    consumer::{Config, IcedMessage},
+    producer::wifi::{self, Wifi},
+    util::{color::Colormap, glob::Glob},
+};
+
+use super::Consumer;
+
+#[derive(Deserialize, Serialize)]
+pub struct WifiConfig {
+    /// Interface to show, by name or glob. When unset, every wireless
+    /// interface is shown.
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Keyed by signal quality, from 0.0 (-90 dBm) to 1.0 (-30 dBm).
+    pub colormap: Colormap,
+    /// Color of the SSID and frequency.
+    pub color: Color,
+    /// Color of the text shown when not connected.
+    pub disconnected_color: Color,
+    pub width: f32,
+    pub height: f32,
+    pub spacing: f32,
+    #[serde(default)]
+    pub show_frequency: bool,
+    /// Hide interfaces that aren't connected, instead of showing them as
+    /// disconnected.
+    #[serde(default)]
+    pub hide_disconnected: bool,
+}
+
+#[typetag::serde]
+impl Config for WifiConfig {
+    fn into_consumer(self: Box<Self>) -> Box<dyn Consumer> {
+        let receiver = wifi::listen();
+        let interface = self.interface.as_deref().map(Glob::new);
+
+        Box::new(WifiConsumer {
+            receiver,
+            interface,
+            config: *self,
+        })
+    }
+}
+
+pub struct WifiConsumer {
+    receiver: watch::Receiver<wifi::Message>,
+    interface: Option<Glob>,
+    config: WifiConfig,
+}
+
+impl WifiConsumer {
+    fn bar(&self, quality: f32) -> ProgressBar<'_, Theme> {
+        let color = self.config.colormap.map(quality);
+        iced::widget::progress_bar(0.0..=1.0, quality)
+            .length(Length::Fixed(self.config.width))
+            .girth(Length::Fixed(self.config.height))
+            .style(move |theme: &Theme| iced::widget::progress_bar::Style {
+                bar: color.into(),
+                border: iced::Border {
+                    color,
+                    width: 1.0,
+                    radius: Radius::new(0.0),
+                },
+                background: theme.palette().background.into(),
+            })
+    }
+
+    fn wifi(&self, wifi: &Wifi) -> Option<Element<'_, IcedMessage>> {
+        let Some(ssid) = &wifi.ssid else {
+            if self.config.hide_disconnected {
+                return None;
+            }
+            let label = format!("{}: --", wifi.interface);
+            return Some(text(label).color(self.config.disconnected_color).into());
+        };
+
+        let frequency = wifi
+            .frequency
+            .filter(|_| self.config.show_frequency)
+            .map(|mhz| text(format!("{:.1} GHz", mhz as f32 / 1000.0)).color(self.config.color));
+        Some(
+            row![
+                text(ssid.clone()).color(self.config.color),
+                wifi.quality().map(|q| self.bar(q)),
+                frequency,
+            ]
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing)
+            .into(),
+        )
+    }
+}
+
+#[async_trait]
+impl Consumer for WifiConsumer {
+    async fn consume(&mut self) {
+        self.receiver.changed().await.unwrap();
+    }
+
+    fn render(&self, _: &str) -> Element<'_, IcedMessage> {
+        let msg = self.receiver.borrow();
+        let wifis = msg
+            .interfaces
+            .iter()
+            .filter(|w| {
+                self.interface
+                    .as_ref()
+                    .is_none_or(|g| g.is_match(&w.interface))
+            })
+            .filter_map(|w| self.wifi(w));
+        Row::with_children(wifis)
+            .align_y(Vertical::Center)
+            .spacing(self.config.spacing)
+            .into()
+    }
+}
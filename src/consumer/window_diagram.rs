@@ -1,15 +1,24 @@
-use std::{fs, path::PathBuf};
+use std::{
+    collections::HashSet,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
 use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
-use iced::{Color, Element, Length, widget::Svg};
+use iced::{
+    Color, Element, Length,
+    widget::{Row, Svg, mouse_area},
+};
 use serde::{Deserialize, Serialize};
 use tokio::sync::watch;
 
 use crate::{
     APP,
-    consumer::{Config, IcedMessage},
-    producer::niri::{self, Output},
+    consumer::{Action, Config, IcedMessage},
+    producer::niri,
+    util::icon,
 };
 
 use super::Consumer;
@@ -21,6 +30,21 @@ pub struct WindowDiagramConfig {
     pub background: Color,
     pub urgent: Color,
     pub visible: Color,
+    #[serde(default)]
+    pub workspaces: DiagramWorkspaces,
+}
+
+/// Which workspaces on the output get a diagram. Each with windows gets its
+/// own, side by side in order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagramWorkspaces {
+    #[default]
+    Active,
+    All,
+    /// All of them while the pointer is over the diagram, and otherwise the
+    /// active one.
+    Hover,
 }
 
 #[typetag::serde]
@@ -30,6 +54,7 @@ impl Config for WindowDiagramConfig {
 
         Box::new(WindowDiagramConsumer {
             receiver,
+            hovered: Arc::default(),
             config: *self,
         })
     }
@@ -37,6 +62,9 @@ impl Config for WindowDiagramConfig {
 
 pub struct WindowDiagramConsumer {
     receiver: watch::Receiver<niri::Message>,
+    /// The outputs whose diagram the pointer is over. Every bar shares the
+    /// consumer.
+    hovered: Arc<Mutex<HashSet<String>>>,
     config: WindowDiagramConfig,
 }
 
@@ -45,14 +73,18 @@ struct Window {
     app_id: Option<String>,
     focused: bool,
     urgent: bool,
+    /// Where its tile starts horizontally in the output's view of the
+    /// workspace.
+    view_x: Option<f64>,
 }
 
-#[allow(dead_code)]
 struct FloatingWindow {
+    /// Position in the output's view of the workspace.
     x: f64,
     y: f64,
     width: f64,
     height: f64,
+    app_id: Option<String>,
     focused: bool,
     urgent: bool,
 }
@@ -65,17 +97,23 @@ struct Column {
 #[derive(Default)]
 struct Windows {
     scale_factor: f64,
+    /// The output's size, if known.
+    size: Option<(f64, f64)>,
     cols: Vec<Column>,
-    #[allow(dead_code)]
     floaters: Vec<FloatingWindow>,
+    /// The part of the workspace shown on the output, as its left edge and
+    /// width, relative to the left of the first column.
+    view: Option<(f64, f64)>,
 }
 
 impl Windows {
-    fn new(output: &Output) -> Self {
+    /// `windows` are those of one workspace, sorted by position, and `size`
+    /// that of the output it's on.
+    fn new(windows: &[niri_ipc::Window], size: Option<(u32, u32)>) -> Self {
         let mut cols = Vec::new();
         let mut floaters = Vec::new();
 
-        for window in output.workspace_windows.iter() {
+        for window in windows.iter() {
             let layout = &window.layout;
             match layout.pos_in_scrolling_layout {
                 Some((col, _row)) => {
@@ -84,6 +122,7 @@ impl Windows {
                         app_id: window.app_id.clone(),
                         focused: window.is_focused,
                         urgent: window.is_urgent,
+                        view_x: layout.tile_pos_in_workspace_view.map(|(x, _)| x),
                     };
 
                     if col > cols.len() {
@@ -95,105 +134,110 @@ impl Windows {
                         cols.last_mut().unwrap().windows.push(window);
                     }
                 }
-                None => floaters.push(FloatingWindow {
-                    x: layout.tile_pos_in_workspace_view.unwrap().0,
-                    y: layout.tile_pos_in_workspace_view.unwrap().1,
-                    width: layout.tile_size.0,
-                    height: layout.tile_size.1,
-                    focused: window.is_focused,
-                    urgent: window.is_urgent,
-                }),
+                None => {
+                    let Some((x, y)) = layout.tile_pos_in_workspace_view else {
+                        continue;
+                    };
+                    floaters.push(FloatingWindow {
+                        x,
+                        y,
+                        width: layout.tile_size.0,
+                        height: layout.tile_size.1,
+                        app_id: window.app_id.clone(),
+                        focused: window.is_focused,
+                        urgent: window.is_urgent,
+                    })
+                }
             }
         }
 
-        let output_height = cols
-            .iter()
-            .map(|col| col.windows.iter().map(|w| w.height).sum::<f64>())
-            .max_by(|a, b| a.total_cmp(b))
-            .unwrap_or_default();
+        let size = size.map(|(width, height)| (width as f64, height as f64));
+        // Without tiles, the output stands in for them.
+        let output_height = match tiled_height(&cols) {
+            0.0 => size.map(|(_, height)| height).unwrap_or_default(),
+            height => height,
+        };
         let scale_factor = output_height / (APP.config.height as f64);
 
+        let view = size.and_then(|(width, _)| Some((view_left(&cols)?, width)));
+
         Windows {
             scale_factor,
+            size,
             cols,
             floaters,
+            view,
         }
     }
 }
 
-fn color_to_svg(c: Color) -> String {
-    format!(
-        "rgb({},{},{})",
-        (c.r * 255.0) as u8,
-        (c.g * 255.0) as u8,
-        (c.b * 255.0) as u8
-    )
-}
+impl Windows {
+    /// Where a floating window is, relative to the first column.
+    fn floater_rect(&self, win: &FloatingWindow) -> (f64, f64, f64, f64) {
+        let view_left = self.view.map(|(left, _)| left).unwrap_or_default();
+        (view_left + win.x, win.y, win.width, win.height)
+    }
 
-fn find_icon(app_id: &str) -> Option<PathBuf> {
-    let extensions = ["svg", "png"];
-
-    // Standard icon locations
-    let icon_dirs = [
-        "/run/current-system/sw/share/icons/hicolor/scalable/apps",
-        "/run/current-system/sw/share/icons/hicolor/256x256/apps",
-        "/run/current-system/sw/share/icons/hicolor/128x128/apps",
-        "/run/current-system/sw/share/icons/hicolor/64x64/apps",
-        "/run/current-system/sw/share/icons/hicolor/48x48/apps",
-        "/run/current-system/sw/share/pixmaps",
-    ];
-
-    for dir in &icon_dirs {
-        for ext in &extensions {
-            let path = PathBuf::from(dir).join(format!("{}.{}", app_id, ext));
-            if path.exists() {
-                return Some(path);
-            }
-        }
+    /// The area to draw, relative to the first column, as its left, top,
+    /// right and bottom: the columns, or else the output, and the floating
+    /// windows wherever they stick out.
+    fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        let tiled = if self.cols.is_empty() {
+            self.size.map(|(width, height)| (0.0, 0.0, width, height))
+        } else {
+            let width = self.cols.iter().map(|c| c.width).sum();
+            Some((0.0, 0.0, width, tiled_height(&self.cols)))
+        };
+        self.floaters
+            .iter()
+            .map(|win| {
+                let (x, y, w, h) = self.floater_rect(win);
+                (x, y, x + w, y + h)
+            })
+            .chain(tiled)
+            .reduce(|(l1, t1, r1, b1), (l2, t2, r2, b2)| {
+                (l1.min(l2), t1.min(t2), r1.max(r2), b1.max(b2))
+            })
     }
+}
 
-    // On NixOS, try to find icon in the app's nix store path
-    if let Ok(bin_path) = std::process::Command::new("which").arg(app_id).output()
-        && bin_path.status.success()
-    {
-        let bin_path = String::from_utf8_lossy(&bin_path.stdout).trim().to_string();
-        if let Ok(resolved) = fs::canonicalize(&bin_path) {
-            // Go up to the nix store package root (2 levels up from bin/)
-            if let Some(store_path) = resolved.ancestors().nth(2) {
-                // Search for icons in share/icons and lib/*/logo
-                let search_dirs = [
-                    store_path.join("share/icons/hicolor/scalable/apps"),
-                    store_path.join("share/icons/hicolor/256x256/apps"),
-                    store_path.join("share/icons/hicolor/128x128/apps"),
-                    store_path.join("share/icons/hicolor/64x64/apps"),
-                    store_path.join("share/icons/hicolor/48x48/apps"),
-                    store_path.join("share/pixmaps"),
-                ];
-
-                for dir in &search_dirs {
-                    for ext in &extensions {
-                        let path = dir.join(format!("{}.{}", app_id, ext));
-                        if path.exists() {
-                            return Some(path);
-                        }
-                    }
-                }
+/// The height of the tallest column.
+fn tiled_height(cols: &[Column]) -> f64 {
+    cols.iter()
+        .map(|col| col.windows.iter().map(|w| w.height).sum::<f64>())
+        .max_by(|a, b| a.total_cmp(b))
+        .unwrap_or_default()
+}
 
-                // Also try lib/{app}/logo/{app}.png pattern (used by kitty)
-                for ext in &extensions {
-                    let path = store_path.join(format!("lib/{}/logo/{}.{}", app_id, app_id, ext));
-                    if path.exists() {
-                        return Some(path);
-                    }
-                }
+/// Where the view starts, from a tile's position in it: the focused one's,
+/// or else the first one's. The diagram leaves out gaps, so a tile close to
+/// the view's edge gives the best estimate.
+fn view_left(cols: &[Column]) -> Option<f64> {
+    let mut x = 0.0;
+    let mut left = None;
+    for col in cols {
+        for win in &col.windows {
+            if let Some(view_x) = win.view_x
+                && (left.is_none() || win.focused)
+            {
+                left = Some(x - view_x);
             }
         }
+        x += col.width;
     }
+    left
+}
 
-    None
+fn color_to_svg(c: Color) -> String {
+    format!(
+        "rgb({},{},{})",
+        (c.r * 255.0) as u8,
+        (c.g * 255.0) as u8,
+        (c.b * 255.0) as u8
+    )
 }
 
-fn load_icon_data_url(path: &PathBuf) -> Option<String> {
+fn load_icon_data_url(path: &Path) -> Option<String> {
     let data = fs::read(path).ok()?;
     let ext = path.extension()?.to_str()?;
 
@@ -206,6 +250,34 @@ fn load_icon_data_url(path: &PathBuf) -> Option<String> {
     Some(format!("data:{};base64,{}", mime, BASE64.encode(&data)))
 }
 
+/// Draws a window's tile, with its app's icon in the middle.
+fn push_window(
+    svg: &mut String,
+    (x, y, w, h): (f64, f64, f64, f64),
+    app_id: Option<&str>,
+    fill: &str,
+    border: &str,
+) {
+    svg.push_str(&format!(
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="{}" stroke-width="1.5"/>"#,
+        x, y, w, h, fill, border
+    ));
+
+    // Draw icon if found
+    if let Some(app_id) = app_id
+        && let Some(icon_path) = icon::app_icon(app_id)
+        && let Some(data_url) = load_icon_data_url(&icon_path)
+    {
+        let icon_size = h.min(w) * 0.8;
+        let icon_x = x + (w - icon_size) / 2.0;
+        let icon_y = y + (h - icon_size) / 2.0;
+        svg.push_str(&format!(
+            r#"<image x="{}" y="{}" width="{}" height="{}" href="{}"/>"#,
+            icon_x, icon_y, icon_size, icon_size, data_url
+        ));
+    }
+}
+
 #[async_trait]
 impl Consumer for WindowDiagramConsumer {
     async fn consume(&mut self) {
@@ -215,37 +287,85 @@ impl Consumer for WindowDiagramConsumer {
     fn render(&self, output_name: &str) -> Element<'_, IcedMessage> {
         let msg = self.receiver.borrow();
         let Some(output) = msg.outputs.get(output_name) else {
-            return Svg::new(iced::widget::svg::Handle::from_memory(
-                b"<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>".to_vec(),
-            ))
-            .width(Length::Shrink)
-            .height(Length::Fill)
-            .into();
+            return empty();
         };
-        let windows = Windows::new(output);
-
-        if windows.cols.is_empty() {
-            return Svg::new(iced::widget::svg::Handle::from_memory(
-                b"<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>".to_vec(),
-            ))
-            .width(Length::Shrink)
-            .height(Length::Fill)
-            .into();
+        let all = match self.config.workspaces {
+            DiagramWorkspaces::Active => false,
+            DiagramWorkspaces::All => true,
+            DiagramWorkspaces::Hover => self.hovered.lock().unwrap().contains(output_name),
+        };
+        let diagrams: Element<'_, IcedMessage> = if all {
+            let diagrams = output.workspaces.iter().filter_map(|ws| {
+                let windows = output.windows_by_workspace.get(&ws.id)?;
+                Some(self.diagram(&Windows::new(windows, output.size)))
+            });
+            Row::with_children(diagrams)
+                .spacing(APP.config.spacing / 2.0)
+                .into()
+        } else {
+            self.diagram(&Windows::new(&output.workspace_windows, output.size))
+        };
+        if self.config.workspaces != DiagramWorkspaces::Hover {
+            return diagrams;
         }
+        mouse_area(diagrams)
+            .on_enter(self.hover(output_name, true))
+            .on_exit(self.hover(output_name, false))
+            .into()
+    }
+}
 
-        let total_width: f64 = windows.cols.iter().map(|c| c.width).sum();
+fn empty<'a>() -> Element<'a, IcedMessage> {
+    Svg::new(iced::widget::svg::Handle::from_memory(
+        b"<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>".to_vec(),
+    ))
+    .width(Length::Shrink)
+    .height(Length::Fill)
+    .into()
+}
+
+impl WindowDiagramConsumer {
+    /// Records whether the pointer is over the diagram on `output`. It's
+    /// redrawn once the action has run.
+    fn hover(&self, output: &str, hovered: bool) -> IcedMessage {
+        let outputs = self.hovered.clone();
+        let output = output.to_string();
+        IcedMessage::Action(Action::new(move || {
+            let mut outputs = outputs.lock().unwrap();
+            if hovered {
+                outputs.insert(output.clone());
+            } else {
+                outputs.remove(&output);
+            }
+            async {}
+        }))
+    }
+
+    fn diagram(&self, windows: &Windows) -> Element<'_, IcedMessage> {
         let scale = windows.scale_factor;
+        let Some((left, top, right, bottom)) = windows.bounds().filter(|_| scale > 0.0) else {
+            return empty();
+        };
+
+        let total_width: f64 = windows.cols.iter().map(|c| c.width).sum();
         let scaled_width = total_width / scale;
         let scaled_height = APP.config.height as f64;
+        // Floating windows sticking out of the columns shrink the diagram to
+        // make room for them.
+        let (view_width, view_height) =
+            ((right - left) / scale + 1.0, (bottom - top) / scale + 1.0);
+        let fit = (scaled_height + 1.0) / view_height;
 
         let config = &self.config;
         let border_color = color_to_svg(config.border);
 
         // Build SVG content (add 1 to viewBox to account for stroke width)
         let mut svg = format!(
-            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}">"#,
-            scaled_width + 1.0,
-            scaled_height + 1.0
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+            left / scale,
+            top / scale,
+            view_width,
+            view_height
         );
 
         // Draw tiled columns
@@ -256,45 +376,141 @@ impl Consumer for WindowDiagramConsumer {
 
             for win in &col.windows {
                 let h = win.height / scale;
-                let fill = if win.urgent {
-                    color_to_svg(config.urgent)
-                } else if win.focused {
-                    color_to_svg(config.focused)
-                } else {
-                    color_to_svg(config.background)
-                };
+                let fill = self.fill(win.focused, win.urgent);
+                push_window(
+                    &mut svg,
+                    (x, y, w, h),
+                    win.app_id.as_deref(),
+                    &fill,
+                    &border_color,
+                );
+                y += h;
+            }
+            x += w;
+        }
 
+        // Outline the columns that are on screen
+        if let Some((left, width)) = windows.view {
+            let start = (left / scale).max(0.0);
+            let end = ((left + width) / scale).min(scaled_width);
+            if end > start {
                 svg.push_str(&format!(
-                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="{}" stroke-width="1.5"/>"#,
-                    x, y, w, h, fill, border_color
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+                    start + 0.5,
+                    0.5,
+                    end - start,
+                    scaled_height,
+                    color_to_svg(config.visible)
                 ));
-
-                // Draw icon if found
-                if let Some(app_id) = &win.app_id
-                    && let Some(icon_path) = find_icon(app_id)
-                    && let Some(data_url) = load_icon_data_url(&icon_path)
-                {
-                    let icon_size = h.min(w) * 0.8;
-                    let icon_x = x + (w - icon_size) / 2.0;
-                    let icon_y = y + (h - icon_size) / 2.0;
-                    svg.push_str(&format!(
-                        r#"<image x="{}" y="{}" width="{}" height="{}" href="{}"/>"#,
-                        icon_x, icon_y, icon_size, icon_size, data_url
-                    ));
-                }
-
-                y += h;
             }
-            x += w;
         }
 
-        // TODO: Draw floating windows on top once niri provides tile_pos_in_workspace_view for tiled windows
+        // Draw floating windows on top, where they are on screen
+        for win in &windows.floaters {
+            let fill = self.fill(win.focused, win.urgent);
+            let (x, y, w, h) = windows.floater_rect(win);
+            push_window(
+                &mut svg,
+                (x / scale, y / scale, w / scale, h / scale),
+                win.app_id.as_deref(),
+                &fill,
+                &border_color,
+            );
+        }
 
         svg.push_str("</svg>");
 
         Svg::new(iced::widget::svg::Handle::from_memory(svg.into_bytes()))
-            .width(Length::Fixed((scaled_width + 1.0) as f32))
+            .width(Length::Fixed((view_width * fit) as f32))
             .height(Length::Fill)
             .into()
     }
+
+    fn fill(&self, focused: bool, urgent: bool) -> String {
+        if urgent {
+            color_to_svg(self.config.urgent)
+        } else if focused {
+            color_to_svg(self.config.focused)
+        } else {
+            color_to_svg(self.config.background)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(width: f64, windows: &[(Option<f64>, bool)]) -> Column {
+        Column {
+            width,
+            windows: windows
+                .iter()
+                .map(|&(view_x, focused)| Window {
+                    height: 100.0,
+                    app_id: None,
+                    focused,
+                    urgent: false,
+                    view_x,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn view_follows_the_focused_tile() {
+        // Scrolled so the second column starts 50 into the view.
+        let cols = [
+            column(300.0, &[(Some(-250.0), false)]),
+            column(400.0, &[(Some(60.0), false), (Some(50.0), true)]),
+            column(500.0, &[(None, false)]),
+        ];
+        assert_eq!(view_left(&cols), Some(250.0));
+
+        // Without a focused tile, the first positioned one is used.
+        let cols = [
+            column(300.0, &[(None, false)]),
+            column(400.0, &[(Some(20.0), false)]),
+        ];
+        assert_eq!(view_left(&cols), Some(280.0));
+
+        assert_eq!(view_left(&[column(300.0, &[(None, false)])]), None);
+    }
+
+    fn floater(x: f64, y: f64, width: f64, height: f64) -> FloatingWindow {
+        FloatingWindow {
+            x,
+            y,
+            width,
+            height,
+            app_id: None,
+            focused: false,
+            urgent: false,
+        }
+    }
+
+    #[test]
+    fn bounds_cover_floaters() {
+        let windows = Windows {
+            scale_factor: 1.0,
+            size: Some((1000.0, 200.0)),
+            cols: vec![column(300.0, &[(Some(-250.0), true)])],
+            floaters: vec![floater(-100.0, 50.0, 200.0, 200.0)],
+            view: Some((250.0, 1000.0)),
+        };
+        // The floater is placed in the view, which starts 250 into the
+        // columns, and hangs below them.
+        assert_eq!(windows.bounds(), Some((0.0, 0.0, 350.0, 250.0)));
+
+        // Without columns, the output is drawn, and floaters in it.
+        let windows = Windows {
+            scale_factor: 1.0,
+            size: Some((1000.0, 200.0)),
+            floaters: vec![floater(900.0, -20.0, 200.0, 100.0)],
+            ..Default::default()
+        };
+        assert_eq!(windows.bounds(), Some((0.0, -20.0, 1100.0, 200.0)));
+
+        assert_eq!(Windows::default().bounds(), None);
+    }
 }
@@ -1,17 +1,19 @@
 use std::hash::{Hash, Hasher};
 
 use futures::{SinkExt, Stream};
+use iced::alignment::Horizontal;
 use iced::theme::Palette;
-use iced::widget::{Row, container, row};
-use iced::{Element, Font, Length, Subscription, Task, Theme};
+use iced::widget::{Row, column, container, mouse_area, row, sensor};
+use iced::{Color, Element, Font, Length, Size, Subscription, Task, Theme};
+use iced_layershell::actions::ActionCallback;
 use iced_layershell::application;
 use iced_layershell::reexport::Anchor;
 use iced_layershell::settings::{LayerShellSettings, Settings};
 use tokio::sync::watch;
 
 use crate::APP;
-use crate::consumer::IcedMessage;
-use crate::producer::{niri, tick};
+use crate::consumer::{Consumer, IcedMessage, PopupId};
+use crate::producer::{self, niri, tick};
 
 pub fn run(output: String, shutdown: watch::Receiver<bool>) -> eyre::Result<()> {
     // Leak to deal with iced's boot nonsense.
@@ -22,6 +24,12 @@ pub fn run(output: String, shutdown: watch::Receiver<bool>) -> eyre::Result<()>
         move || BarInstance {
             output: o.to_owned(),
             shutdown: shutdown.clone(),
+            popup: None,
+            grown: false,
+            hidden: false,
+            width: 0.0,
+            toasts: None,
+            niri: niri::listen(),
         },
         namespace,
         update,
@@ -31,8 +39,11 @@ pub fn run(output: String, shutdown: watch::Receiver<bool>) -> eyre::Result<()>
     // Set the style directly: iced_layershell computes the initial style from
     // the *default* (light) theme and only applies our theme after the first
     // message, which flashes the bar white on every surface creation.
+    //
+    // The surface is transparent so that, while it's grown to make room for
+    // a popup or toasts, only the bar itself and those are drawn.
     .style(|_, theme| iced::theme::Style {
-        background_color: APP.config.background,
+        background_color: Color::TRANSPARENT,
         text_color: theme.palette().text,
     })
     .subscription(subscription)
@@ -58,19 +69,131 @@ pub fn run(output: String, shutdown: watch::Receiver<bool>) -> eyre::Result<()>
 struct BarInstance {
     output: String,
     shutdown: watch::Receiver<bool>,
+    popup: Option<PopupId>,
+    /// Whether the surface has grown to make room below the bar.
+    grown: bool,
+    /// Whether the bar is hidden for niri's overview.
+    hidden: bool,
+    /// The surface's width, as the bar was last laid out.
+    width: f32,
+    /// The size and alignment of the toasts, as last laid out.
+    toasts: Option<(Size, Horizontal)>,
+    niri: watch::Receiver<niri::Message>,
 }
 
 fn namespace() -> String {
     String::from("rustybar")
 }
 
-fn update(_: &mut BarInstance, message: IcedMessage) -> Task<IcedMessage> {
+fn update(instance: &mut BarInstance, message: IcedMessage) -> Task<IcedMessage> {
     match message {
         IcedMessage::Exit => iced::exit(),
+        IcedMessage::Action(action) => Task::perform(action.run(), |()| IcedMessage::A),
+        IcedMessage::TogglePopup(id) => {
+            let open = instance.popup != Some(id);
+            set_popup(instance, open.then_some(id))
+        }
+        IcedMessage::ClosePopup => set_popup(instance, None),
+        // Toasts and the overview come and go with producer updates.
+        IcedMessage::A => Task::batch([hide(instance), resize(instance)]),
+        IcedMessage::Batch(messages) => {
+            Task::batch(messages.into_iter().map(|m| update(instance, m)))
+        }
+        IcedMessage::BarResized(size) => {
+            instance.width = size.width;
+            input_region(instance)
+        }
+        IcedMessage::ToastsResized(size, align) => {
+            instance.toasts = Some((size, align));
+            input_region(instance)
+        }
         _ => Task::none(),
     }
 }
 
+/// Opens or closes a popup.
+fn set_popup(instance: &mut BarInstance, popup: Option<PopupId>) -> Task<IcedMessage> {
+    instance.popup = popup;
+    Task::batch([resize(instance), input_region(instance)])
+}
+
+/// Grows the surface while there's a popup or toasts to show beneath the
+/// bar, and shrinks it back after.
+fn resize(instance: &mut BarInstance) -> Task<IcedMessage> {
+    let grow = instance.popup.is_some() || beneath(|c| c.toasts(&instance.output)).is_some();
+    if grow == instance.grown {
+        return Task::none();
+    }
+    instance.grown = grow;
+    let height = if grow {
+        APP.config.height + APP.config.popup_height
+    } else {
+        instance.toasts = None;
+        APP.config.height
+    };
+    Task::batch([
+        Task::done(IcedMessage::SizeChange((0, height))),
+        input_region(instance),
+    ])
+}
+
+/// Hides the bar while niri's overview is open, if configured to, giving up
+/// its exclusive zone and input so that what's beneath can use the space.
+/// Closes any popup, so that the surface shrinks back too.
+fn hide(instance: &mut BarInstance) -> Task<IcedMessage> {
+    let hide = APP.config.hide_in_overview && instance.niri.borrow().overview_open;
+    if hide == instance.hidden {
+        return Task::none();
+    }
+    instance.hidden = hide;
+    if hide {
+        instance.popup = None;
+    }
+    let zone = if hide { 0 } else { APP.config.height as i32 };
+    Task::batch([
+        Task::done(IcedMessage::ExclusiveZoneChange(zone)),
+        input_region(instance),
+    ])
+}
+
+/// Space between the toasts and the sides of the surface.
+const TOAST_PADDING: f32 = 8.0;
+
+/// Limits input to the bar and the toasts, so that clicks on the transparent
+/// rest of a grown surface reach what's below it. While a popup is open, the
+/// whole surface takes input, so that clicks beside the popup close it. While
+/// the bar is hidden, nothing does.
+fn input_region(instance: &BarInstance) -> Task<IcedMessage> {
+    let height = APP.config.height as i32;
+    let (hidden, popup) = (instance.hidden, instance.popup.is_some());
+    let (width, toasts) = (instance.width, instance.toasts);
+    let callback = ActionCallback::new(move |region| {
+        region.subtract(0, 0, i32::MAX, i32::MAX);
+        if hidden {
+            return;
+        }
+        if popup {
+            region.add(0, 0, i32::MAX, i32::MAX);
+            return;
+        }
+        region.add(0, 0, i32::MAX, height);
+        if let Some((size, align)) = toasts {
+            let x = match align {
+                Horizontal::Left => TOAST_PADDING,
+                Horizontal::Center => (width - size.width) / 2.0,
+                Horizontal::Right => width - TOAST_PADDING - size.width,
+            };
+            region.add(
+                x.floor() as i32,
+                height,
+                size.width.ceil() as i32,
+                size.height.ceil() as i32,
+            );
+        }
+    });
+    Task::done(IcedMessage::SetInputRegion(callback))
+}
+
 fn theme(_: &BarInstance) -> Theme {
     let mut palette = Palette::DARK;
     palette.background = APP.config.background;
@@ -105,6 +228,7 @@ fn worker(seed: &WorkerSeed) -> impl Stream<Item = IcedMessage> + use<> {
     iced::stream::channel(1, async move |mut output| {
         let mut tick_receiver = tick::listen();
         let mut niri_receiver = niri::listen();
+        let mut updates = producer::updates();
         loop {
             let stop = *shutdown.borrow_and_update();
             if stop {
@@ -114,6 +238,7 @@ fn worker(seed: &WorkerSeed) -> impl Stream<Item = IcedMessage> + use<> {
             tokio::select! {
                 _ = tick_receiver.changed() => {},
                 _ = niri_receiver.changed() => {},
+                _ = updates.changed() => {},
                 _ = shutdown.changed() => continue,
             }
             output.send(IcedMessage::A).await.unwrap();
@@ -122,24 +247,89 @@ fn worker(seed: &WorkerSeed) -> impl Stream<Item = IcedMessage> + use<> {
 }
 
 fn view(instance: &BarInstance) -> Element<'_, IcedMessage> {
+    if instance.hidden {
+        return row![].into();
+    }
+    let overview = instance.niri.borrow().overview_open;
+    let background = APP
+        .config
+        .overview_background
+        .filter(|_| overview)
+        .unwrap_or(APP.config.background);
+    let bar = sensor(
+        container(bar(instance))
+            .height(Length::Fixed(APP.config.height as f32))
+            .style(move |_| container::background(background)),
+    )
+    .on_resize(IcedMessage::BarResized);
+    let Some(id) = instance.popup else {
+        return match beneath(|c| c.toasts(&instance.output)) {
+            Some((toasts, align)) => column![
+                bar,
+                container(
+                    sensor(toasts)
+                        .on_resize(move |size| { IcedMessage::ToastsResized(size, align) })
+                )
+                .width(Length::Fill)
+                .padding([0.0, TOAST_PADDING])
+                .align_x(align)
+            ]
+            .into(),
+            None => bar.into(),
+        };
+    };
+
+    // Clicks on the popup are swallowed; clicks beside it close it.
+    let (popup, align) = match beneath(|c| c.popup(id, &instance.output)) {
+        Some((popup, align)) => (
+            Some(
+                mouse_area(container(popup).padding(8).style(|theme: &Theme| {
+                    container::bordered_box(theme).background(APP.config.background)
+                }))
+                .on_press(IcedMessage::A),
+            ),
+            align,
+        ),
+        None => (None, Horizontal::Left),
+    };
+    let below = container(popup)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .align_x(align);
+    column![bar, mouse_area(below).on_press(IcedMessage::ClosePopup)].into()
+}
+
+/// The first content a consumer has for beneath the bar, aligned to the
+/// consumer's section.
+fn beneath<'a>(
+    content: impl Fn(&'a dyn Consumer) -> Option<Element<'a, IcedMessage>>,
+) -> Option<(Element<'a, IcedMessage>, Horizontal)> {
+    let sections = [
+        (&APP.left, Horizontal::Left),
+        (&APP.center, Horizontal::Center),
+        (&APP.right, Horizontal::Right),
+    ];
+    sections.into_iter().find_map(|(consumers, align)| {
+        consumers
+            .iter()
+            .find_map(|c| content(c.as_ref()))
+            .map(|content| (content, align))
+    })
+}
+
+fn section<'a>(consumers: &'a [Box<dyn Consumer>], output: &'a str) -> Row<'a, IcedMessage> {
+    Row::with_children(consumers.iter().map(|comp| comp.render(output))).spacing(APP.config.spacing)
+}
+
+fn bar(instance: &BarInstance) -> Element<'_, IcedMessage> {
     row![
-        container(
-            Row::with_children(APP.left.iter().map(|comp| comp.render(&instance.output)))
-                .spacing(APP.config.spacing)
-        )
-        .center_y(Length::Fill)
-        .align_left(Length::Fill),
-        container(
-            Row::with_children(APP.center.iter().map(|comp| comp.render(&instance.output)))
-                .spacing(APP.config.spacing)
-        )
-        .center_y(Length::Fill),
-        container(
-            Row::with_children(APP.right.iter().map(|comp| comp.render(&instance.output)))
-                .spacing(APP.config.spacing)
-        )
-        .center_y(Length::Fill)
-        .align_right(Length::Fill),
+        container(section(&APP.left, &instance.output))
+            .center_y(Length::Fill)
+            .align_left(Length::Fill),
+        container(section(&APP.center, &instance.output)).center_y(Length::Fill),
+        container(section(&APP.right, &instance.output))
+            .center_y(Length::Fill)
+            .align_right(Length::Fill),
     ]
     .spacing(APP.config.spacing)
     .into()
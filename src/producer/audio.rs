@@ -0,0 +1,485 @@
+//! The audio server's default sink and source, followed through a [`Backend`].
+//! The real backend drives `pactl`, which speaks to both PulseAudio and
+//! PipeWire's PulseAudio server.
+
+use std::{
+    process::Stdio,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{StreamExt, stream::BoxStream};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, BufReader},
+    process::Command as Process,
+    sync::watch,
+};
+
+use crate::producer::publish;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// How long the server must stay quiet for a burst of events, e.g. from a
+/// volume slider being dragged, to be taken as one.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Default)]
+pub struct Message {
+    /// `None` until the server has been reached.
+    pub audio: Option<Audio>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Audio {
+    pub sinks: Vec<Sink>,
+    pub default_sink: Option<String>,
+    /// Whether the default source is muted, if there is one.
+    pub mic_muted: Option<bool>,
+}
+
+impl Audio {
+    pub fn default_sink(&self) -> Option<&Sink> {
+        let name = self.default_sink.as_ref()?;
+        self.sinks.iter().find(|s| s.name == *name)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Sink {
+    pub name: String,
+    /// Human readable, e.g. "Built-in Audio Analog Stereo".
+    pub description: String,
+    /// Averaged over channels. 1.0 is 100%, and it may go beyond that.
+    pub volume: f32,
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Changes the default sink's volume by `delta`, e.g. -0.05, without
+    /// raising it past `max`.
+    ChangeVolume {
+        delta: f32,
+        max: f32,
+    },
+    ToggleMute,
+    ToggleMicMute,
+    SetDefaultSink(String),
+}
+
+/// Something that can talk to an audio server.
+#[async_trait]
+pub trait Backend: Send + Sync + 'static {
+    async fn read(&self) -> eyre::Result<Audio>;
+
+    /// Yields whenever the server's state may have changed.
+    async fn events(&self) -> eyre::Result<BoxStream<'static, ()>>;
+
+    async fn run(&self, command: Command) -> eyre::Result<()>;
+}
+
+static BACKEND: LazyLock<Arc<dyn Backend>> = LazyLock::new(|| Arc::new(Pactl));
+
+pub fn listen() -> watch::Receiver<Message> {
+    static SENDER: LazyLock<watch::Sender<Message>> = LazyLock::new(|| {
+        let (sender, _) = watch::channel(Message::default());
+
+        let s = sender.clone();
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = follow(BACKEND.as_ref(), &sender).await {
+                    eprintln!("audio: lost the audio server, reconnecting: {e}");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        s
+    });
+
+    SENDER.subscribe()
+}
+
+/// Runs `command` against the audio server. Its effect shows up through the
+/// server's events.
+pub async fn command(command: Command) {
+    if let Err(e) = BACKEND.run(command.clone()).await {
+        eprintln!("audio: {command:?} failed: {e}");
+    }
+}
+
+/// Publishes the server's state, then again after every event.
+async fn follow(backend: &dyn Backend, sender: &watch::Sender<Message>) -> eyre::Result<()> {
+    // Subscribe first, so nothing between the read and the subscription is
+    // missed.
+    let mut events = backend.events().await?;
+    loop {
+        let audio = backend.read().await?;
+        publish(sender, Message { audio: Some(audio) });
+        if events.next().await.is_none() {
+            eyre::bail!("event stream ended");
+        }
+    }
+}
+
+struct Pactl;
+
+/// `volume` changed by `delta`, and kept within 0 and `max`. A volume already
+/// past `max`, raised some other way, isn't lowered by raising it.
+fn changed_volume(volume: f32, delta: f32, max: f32) -> f32 {
+    if delta > 0.0 {
+        (volume + delta).min(max.max(volume))
+    } else {
+        (volume + delta).max(0.0)
+    }
+}
+
+async fn pactl(args: &[&str]) -> eyre::Result<String> {
+    let output = Process::new("pactl").args(args).output().await?;
+    if !output.status.success() {
+        eyre::bail!(
+            "pactl {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[async_trait]
+impl Backend for Pactl {
+    async fn read(&self) -> eyre::Result<Audio> {
+        let sinks = parse_sinks(&pactl(&["-f", "json", "list", "sinks"]).await?)?;
+        let default_sink = pactl(&["get-default-sink"]).await?.trim().to_string();
+        // Having no source at all is fine.
+        let mic_muted = pactl(&["get-source-mute", "@DEFAULT_SOURCE@"])
+            .await
+            .ok()
+            .map(|s| s.trim() == "Mute: yes");
+        Ok(Audio {
+            sinks,
+            default_sink: (!default_sink.is_empty()).then_some(default_sink),
+            mic_muted,
+        })
+    }
+
+    async fn events(&self) -> eyre::Result<BoxStream<'static, ()>> {
+        let mut child = Process::new("pactl")
+            .arg("subscribe")
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let events = subscription(BufReader::new(stdout));
+        Ok(Box::pin(async_stream::stream! {
+            // Keep the child alive, and killed once the stream is dropped.
+            let _child = child;
+            for await event in events {
+                yield event;
+            }
+        }))
+    }
+
+    async fn run(&self, command: Command) -> eyre::Result<()> {
+        match command {
+            Command::ChangeVolume { delta, max } => {
+                let audio = self.read().await?;
+                let Some(sink) = audio.default_sink() else {
+                    return Ok(());
+                };
+                // Relative, so that the channels keep their balance.
+                let change = changed_volume(sink.volume, delta, max) - sink.volume;
+                let change = (change * 100.0).round() as i32;
+                if change == 0 {
+                    return Ok(());
+                }
+                pactl(&["set-sink-volume", "@DEFAULT_SINK@", &format!("{change:+}%")]).await?
+            }
+            Command::ToggleMute => pactl(&["set-sink-mute", "@DEFAULT_SINK@", "toggle"]).await?,
+            Command::ToggleMicMute => {
+                pactl(&["set-source-mute", "@DEFAULT_SOURCE@", "toggle"]).await?
+            }
+            Command::SetDefaultSink(name) => pactl(&["set-default-sink", &name]).await?,
+        };
+        Ok(())
+    }
+}
+
+/// The facility an event line from `pactl subscribe` is about, e.g. "sink"
+/// for "Event 'change' on sink #52".
+fn facility(line: &str) -> Option<&str> {
+    let (_, on) = line.strip_prefix("Event '")?.split_once("' on ")?;
+    Some(on.split_once(" #").map_or(on, |(facility, _)| facility))
+}
+
+/// Yields once for each burst of `pactl subscribe` lines that touches the
+/// sinks, sources or server. Streams and clients come and go constantly,
+/// and don't matter.
+fn subscription<R: AsyncBufRead + Unpin + Send + 'static>(reader: R) -> BoxStream<'static, ()> {
+    let relevant =
+        |line: &str| facility(line).is_some_and(|f| ["sink", "source", "server"].contains(&f));
+    Box::pin(async_stream::stream! {
+        let mut lines = reader.lines();
+        let mut pending = false;
+        loop {
+            let line = if pending {
+                match tokio::time::timeout(DEBOUNCE, lines.next_line()).await {
+                    Ok(line) => line,
+                    Err(_) => {
+                        pending = false;
+                        yield ();
+                        continue;
+                    }
+                }
+            } else {
+                lines.next_line().await
+            };
+            match line {
+                Ok(Some(line)) => pending |= relevant(&line),
+                _ => break,
+            }
+        }
+        if pending {
+            yield ();
+        }
+    })
+}
+
+#[derive(Deserialize)]
+struct PactlSink {
+    name: String,
+    description: String,
+    mute: bool,
+    volume: std::collections::HashMap<String, PactlVolume>,
+}
+
+#[derive(Deserialize)]
+struct PactlVolume {
+    /// Where 65536 is 100%.
+    value: u32,
+}
+
+fn parse_sinks(json: &str) -> eyre::Result<Vec<Sink>> {
+    let sinks: Vec<PactlSink> = serde_json::from_str(json)?;
+    Ok(sinks
+        .into_iter()
+        .map(|sink| {
+            let channels = sink.volume.len().max(1) as f32;
+            let volume = sink.volume.values().map(|v| v.value as f32).sum::<f32>() / channels;
+            Sink {
+                name: sink.name,
+                description: sink.description,
+                volume: volume / 65536.0,
+                muted: sink.mute,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+    use super::*;
+
+    const SINKS: &str = r#"[
+      {"index": 52, "state": "RUNNING", "name": "alsa_output.pci.analog-stereo",
+       "description": "Built-in Audio Analog Stereo", "mute": false,
+       "volume": {"front-left": {"value": 32768, "value_percent": "50%", "db": "-18.06 dB"},
+                  "front-right": {"value": 49152, "value_percent": "75%", "db": "-7.50 dB"}},
+       "balance": 0.2, "base_volume": {"value": 65536}},
+      {"index": 60, "state": "SUSPENDED", "name": "bluez_output.headset",
+       "description": "Headset", "mute": true,
+       "volume": {"mono": {"value": 65536, "value_percent": "100%", "db": "0.00 dB"}}}
+    ]"#;
+
+    #[test]
+    fn parses_pactl_sinks() {
+        let sinks = parse_sinks(SINKS).unwrap();
+        assert_eq!(sinks.len(), 2);
+        assert_eq!(sinks[0].description, "Built-in Audio Analog Stereo");
+        assert_eq!(sinks[0].volume, 0.625);
+        assert!(!sinks[0].muted);
+        assert_eq!(sinks[1].volume, 1.0);
+        assert!(sinks[1].muted);
+    }
+
+    #[test]
+    fn clamps_volume_changes() {
+        assert_eq!(changed_volume(0.5, 0.25, 1.0), 0.75);
+        assert_eq!(changed_volume(0.875, 0.25, 1.0), 1.0);
+        assert_eq!(changed_volume(0.125, -0.25, 1.0), 0.0);
+        // Raised past the maximum some other way: scrolling up leaves it,
+        // and scrolling down lowers it as usual.
+        assert_eq!(changed_volume(1.5, 0.25, 1.0), 1.5);
+        assert_eq!(changed_volume(1.5, -0.25, 1.0), 1.25);
+    }
+
+    #[test]
+    fn parses_event_facilities() {
+        assert_eq!(facility("Event 'change' on sink #52"), Some("sink"));
+        assert_eq!(
+            facility("Event 'new' on sink-input #1024"),
+            Some("sink-input")
+        );
+        assert_eq!(
+            facility("Event 'remove' on source-output #7"),
+            Some("source-output")
+        );
+        assert_eq!(
+            facility("Event 'change' on server #4294967295"),
+            Some("server")
+        );
+        assert_eq!(facility("Event 'change' on card"), Some("card"));
+        assert_eq!(facility("garbage"), None);
+    }
+
+    #[tokio::test]
+    async fn coalesces_event_bursts() {
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        let mut events = subscription(BufReader::new(reader));
+
+        // Streams alone don't count, and a burst of sink changes is one
+        // event.
+        writer
+            .write_all(
+                b"Event 'new' on sink-input #1024\n\
+                  Event 'change' on source-output #7\n\
+                  Event 'change' on sink #52\n\
+                  Event 'change' on sink-input #1024\n\
+                  Event 'change' on sink #52\n",
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.next().await, Some(()));
+
+        writer
+            .write_all(b"Event 'change' on server #4294967295\n")
+            .await
+            .unwrap();
+        drop(writer);
+        assert_eq!(events.next().await, Some(()));
+        assert_eq!(events.next().await, None);
+    }
+
+    /// An audio server in memory, which sends an event after every command.
+    struct FakeBackend {
+        audio: Mutex<Audio>,
+        events: Mutex<Option<mpsc::UnboundedReceiver<()>>>,
+        notify: mpsc::UnboundedSender<()>,
+    }
+
+    impl FakeBackend {
+        fn new(audio: Audio) -> Self {
+            let (notify, events) = mpsc::unbounded_channel();
+            FakeBackend {
+                audio: Mutex::new(audio),
+                events: Mutex::new(Some(events)),
+                notify,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Backend for FakeBackend {
+        async fn read(&self) -> eyre::Result<Audio> {
+            Ok(self.audio.lock().unwrap().clone())
+        }
+
+        async fn events(&self) -> eyre::Result<BoxStream<'static, ()>> {
+            let mut events = self.events.lock().unwrap().take().unwrap();
+            Ok(Box::pin(async_stream::stream! {
+                while let Some(()) = events.recv().await {
+                    yield ();
+                }
+            }))
+        }
+
+        async fn run(&self, command: Command) -> eyre::Result<()> {
+            let mut audio = self.audio.lock().unwrap();
+            match command {
+                Command::ToggleMute => {
+                    let name = audio.default_sink.clone();
+                    for sink in audio
+                        .sinks
+                        .iter_mut()
+                        .filter(|s| Some(&s.name) == name.as_ref())
+                    {
+                        sink.muted = !sink.muted;
+                    }
+                }
+                Command::ChangeVolume { delta, max } => {
+                    let name = audio.default_sink.clone();
+                    for sink in audio
+                        .sinks
+                        .iter_mut()
+                        .filter(|s| Some(&s.name) == name.as_ref())
+                    {
+                        sink.volume = changed_volume(sink.volume, delta, max);
+                    }
+                }
+                Command::ToggleMicMute => {
+                    audio.mic_muted = audio.mic_muted.map(|muted| !muted);
+                }
+                Command::SetDefaultSink(name) => audio.default_sink = Some(name),
+            }
+            self.notify.send(()).unwrap();
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_server_events() {
+        let backend = Arc::new(FakeBackend::new(Audio {
+            sinks: parse_sinks(SINKS).unwrap(),
+            default_sink: Some("alsa_output.pci.analog-stereo".into()),
+            mic_muted: Some(false),
+        }));
+        let (sender, mut receiver) = watch::channel(Message::default());
+        let b = backend.clone();
+        tokio::spawn(async move { follow(b.as_ref(), &sender).await });
+
+        let default_sink = async |receiver: &mut watch::Receiver<Message>| {
+            receiver.changed().await.unwrap();
+            let msg = receiver.borrow_and_update();
+            msg.audio.as_ref().unwrap().default_sink().cloned().unwrap()
+        };
+
+        let sink = default_sink(&mut receiver).await;
+        assert_eq!(sink.description, "Built-in Audio Analog Stereo");
+        assert!(!sink.muted);
+
+        backend.run(Command::ToggleMute).await.unwrap();
+        assert!(default_sink(&mut receiver).await.muted);
+
+        backend
+            .run(Command::ChangeVolume {
+                delta: 0.125,
+                max: 1.0,
+            })
+            .await
+            .unwrap();
+        assert_eq!(default_sink(&mut receiver).await.volume, 0.75);
+
+        backend.run(Command::ToggleMicMute).await.unwrap();
+        receiver.changed().await.unwrap();
+        assert_eq!(
+            receiver
+                .borrow_and_update()
+                .audio
+                .as_ref()
+                .unwrap()
+                .mic_muted,
+            Some(true)
+        );
+
+        backend
+            .run(Command::SetDefaultSink("bluez_output.headset".into()))
+            .await
+            .unwrap();
+        assert_eq!(default_sink(&mut receiver).await.description, "Headset");
+    }
+}
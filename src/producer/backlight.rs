@@ -0,0 +1,203 @@
+//! Display backlights from `/sys/class/backlight`. Brightness changes are
+//! picked up with inotify, and set through logind, which lets the session's
+//! user change them without write access to sysfs.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use futures::StreamExt;
+use inotify::{Inotify, WatchMask};
+use tokio::sync::{OnceCell, watch};
+use zbus::Connection;
+
+use crate::producer::publish;
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+/// The lowest brightness scrolling goes to, as a fraction of the maximum, so
+/// that scrolling down never turns the screen off.
+const MIN_BRIGHTNESS: f32 = 0.01;
+
+#[derive(Debug, Default)]
+pub struct Message {
+    /// Every backlight, sorted by name.
+    pub devices: Vec<Backlight>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Backlight {
+    pub name: String,
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+impl Backlight {
+    pub fn fraction(&self) -> f32 {
+        if self.max_brightness == 0 {
+            0.0
+        } else {
+            self.brightness as f32 / self.max_brightness as f32
+        }
+    }
+
+    /// The brightness after moving `step` (a fraction of the maximum, negative
+    /// to dim), clamped to the valid range.
+    fn stepped(&self, step: f32) -> u32 {
+        let max = self.max_brightness as f32;
+        let target = (self.fraction() + step).clamp(MIN_BRIGHTNESS, 1.0);
+        let target = (target * max).round() as u32;
+        // Make sure tiny steps on coarse backlights still move.
+        match step {
+            s if s > 0.0 && target <= self.brightness => self.brightness + 1,
+            s if s < 0.0 && target >= self.brightness => self.brightness.saturating_sub(1),
+            _ => target,
+        }
+        .clamp(
+            (MIN_BRIGHTNESS * max).ceil() as u32,
+            self.max_brightness.max(1),
+        )
+    }
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Session {
+    fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) -> zbus::Result<()>;
+}
+
+pub fn listen() -> watch::Receiver<Message> {
+    static SENDER: LazyLock<watch::Sender<Message>> = LazyLock::new(|| {
+        let dir = Path::new(BACKLIGHT_DIR);
+        let (sender, _) = watch::channel(Message {
+            devices: read_all(dir),
+        });
+
+        let s = sender.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = watch_devices(dir, &sender).await {
+                eprintln!("backlight: not watching for changes: {e}");
+            }
+        });
+        s
+    });
+
+    SENDER.subscribe()
+}
+
+fn device_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    dirs.sort();
+    dirs
+}
+
+fn read(device: &Path) -> Option<Backlight> {
+    let read = |name| {
+        std::fs::read_to_string(device.join(name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some(Backlight {
+        name: device.file_name()?.to_string_lossy().into_owned(),
+        // `actual_brightness` is what the hardware reports; `brightness` is
+        // only what was last requested.
+        brightness: read("actual_brightness").or_else(|| read("brightness"))?,
+        max_brightness: read("max_brightness")?,
+    })
+}
+
+fn read_all(dir: &Path) -> Vec<Backlight> {
+    device_dirs(dir).iter().filter_map(|d| read(d)).collect()
+}
+
+/// Republishes every backlight whenever one of them changes.
+async fn watch_devices(dir: &Path, sender: &watch::Sender<Message>) -> std::io::Result<()> {
+    let inotify = Inotify::init()?;
+    let mut watched = false;
+    for device in device_dirs(dir) {
+        for file in ["actual_brightness", "brightness"] {
+            watched |= inotify
+                .watches()
+                .add(device.join(file), WatchMask::MODIFY)
+                .is_ok();
+        }
+    }
+    if !watched {
+        return Ok(());
+    }
+
+    let mut events = inotify.into_event_stream([0; 1024])?;
+    while let Some(event) = events.next().await {
+        event?;
+        publish(
+            sender,
+            Message {
+                devices: read_all(dir),
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Changes the named backlight's brightness by `step`, a fraction of its
+/// maximum.
+pub async fn adjust(name: String, step: f32) {
+    static SYSTEM: OnceCell<Connection> = OnceCell::const_new();
+
+    let Some(backlight) = read(&Path::new(BACKLIGHT_DIR).join(&name)) else {
+        return;
+    };
+    let result = async {
+        let connection = SYSTEM.get_or_try_init(Connection::system).await?;
+        SessionProxy::new(connection)
+            .await?
+            .set_brightness("backlight", &name, backlight.stepped(step))
+            .await
+    }
+    .await;
+    if let Err(e) = result {
+        eprintln!("backlight: failed to set brightness of {name}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backlight(brightness: u32, max_brightness: u32) -> Backlight {
+        Backlight {
+            name: "intel_backlight".into(),
+            brightness,
+            max_brightness,
+        }
+    }
+
+    #[test]
+    fn steps_by_fraction_of_max() {
+        assert_eq!(backlight(500, 1000).stepped(0.1), 600);
+        assert_eq!(backlight(500, 1000).stepped(-0.1), 400);
+        assert_eq!(backlight(950, 1000).stepped(0.1), 1000);
+    }
+
+    #[test]
+    fn never_turns_off() {
+        assert_eq!(backlight(30, 1000).stepped(-0.1), 10);
+        assert_eq!(backlight(1, 7).stepped(-0.5), 1);
+    }
+
+    #[test]
+    fn coarse_backlights_still_move() {
+        assert_eq!(backlight(3, 7).stepped(0.05), 4);
+        assert_eq!(backlight(3, 7).stepped(-0.05), 2);
+    }
+}
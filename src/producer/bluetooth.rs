@@ -0,0 +1,452 @@
+//! Bluetooth adapters and devices, from BlueZ on the system bus.
+
+use std::{collections::HashMap, sync::LazyLock, time::Duration};
+
+use futures::{StreamExt, stream};
+use tokio::sync::{OnceCell, watch};
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::{ManagedObjects, ObjectManagerProxy, PropertiesProxy},
+    message,
+    names::{InterfaceName, OwnedInterfaceName},
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+};
+
+use crate::producer::publish;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const SERVICE: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Message {
+    /// Sorted by path, so "hci0" comes first.
+    pub adapters: Vec<Adapter>,
+    /// Every device BlueZ knows of, sorted by name.
+    pub devices: Vec<Device>,
+}
+
+impl Message {
+    pub fn devices_of<'a>(&'a self, adapter: &'a Adapter) -> impl Iterator<Item = &'a Device> {
+        self.devices.iter().filter(|d| d.adapter == adapter.path)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Adapter {
+    /// The adapter's object path, e.g. "/org/bluez/hci0".
+    pub path: String,
+    pub name: String,
+    pub powered: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Device {
+    pub path: String,
+    /// The path of the adapter it's seen through.
+    pub adapter: String,
+    pub name: String,
+    pub address: String,
+    pub paired: bool,
+    pub connected: bool,
+    /// Battery percentage, for devices that report it.
+    pub battery: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Turns the adapter at this path on or off.
+    SetPowered(String, bool),
+    Connect(String),
+    Disconnect(String),
+}
+
+/// An object's interfaces, with their properties.
+type ManagedInterfaces = HashMap<OwnedInterfaceName, HashMap<String, OwnedValue>>;
+
+#[zbus::proxy(interface = "org.bluez.Device1", default_service = "org.bluez")]
+trait Device1 {
+    fn connect(&self) -> zbus::Result<()>;
+    fn disconnect(&self) -> zbus::Result<()>;
+}
+
+pub fn listen() -> watch::Receiver<Message> {
+    static SENDER: LazyLock<watch::Sender<Message>> = LazyLock::new(|| {
+        let (sender, _) = watch::channel(Message::default());
+
+        let s = sender.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let result = match system().await {
+                    Ok(connection) => run(connection, &sender).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("bluetooth: lost BlueZ, reconnecting: {e}");
+                }
+                publish(&sender, Message::default());
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        s
+    });
+
+    SENDER.subscribe()
+}
+
+async fn system() -> zbus::Result<&'static Connection> {
+    static SYSTEM: OnceCell<Connection> = OnceCell::const_new();
+    SYSTEM.get_or_try_init(Connection::system).await
+}
+
+/// Runs `command` against BlueZ. Its effect shows up through BlueZ's signals.
+pub async fn command(command: Command) {
+    let result = async { apply(system().await?, &command).await }.await;
+    if let Err(e) = result {
+        eprintln!("bluetooth: {command:?} failed: {e}");
+    }
+}
+
+async fn apply(connection: &Connection, command: &Command) -> zbus::Result<()> {
+    match command {
+        Command::SetPowered(adapter, powered) => {
+            PropertiesProxy::builder(connection)
+                .destination(SERVICE)?
+                .path(adapter.as_str())?
+                .build()
+                .await?
+                .set(
+                    InterfaceName::from_static_str_unchecked(ADAPTER_INTERFACE),
+                    "Powered",
+                    Value::from(*powered),
+                )
+                .await?;
+        }
+        Command::Connect(device) | Command::Disconnect(device) => {
+            let proxy = Device1Proxy::builder(connection)
+                .path(device.as_str())?
+                .build()
+                .await?;
+            match command {
+                Command::Connect(_) => proxy.connect().await?,
+                _ => proxy.disconnect().await?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Publishes the adapters and devices, then again whenever BlueZ signals a
+/// change. Objects are read once, then kept up to date from the signals.
+async fn run(connection: &Connection, sender: &watch::Sender<Message>) -> zbus::Result<()> {
+    let changes = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .sender(SERVICE)?
+        .build();
+    let owners = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, SERVICE)?
+        .build();
+    let mut signals = stream::select(
+        MessageStream::for_match_rule(changes, connection, None).await?,
+        MessageStream::for_match_rule(owners, connection, None).await?,
+    );
+
+    let manager = ObjectManagerProxy::builder(connection)
+        .destination(SERVICE)?
+        .path("/")?
+        .build()
+        .await?;
+    let mut objects = manager.get_managed_objects().await?;
+    loop {
+        // Most changes, e.g. to signal strength, don't show.
+        let msg = parse(&objects);
+        if *sender.borrow() != msg {
+            publish(sender, msg);
+        }
+        let Some(signal) = signals.next().await else {
+            return Ok(());
+        };
+        if !update(&mut objects, &signal?)? {
+            objects = manager.get_managed_objects().await?;
+        }
+    }
+}
+
+/// Applies a signal from BlueZ to `objects`. Returns false if it can't be,
+/// and they should be read again.
+fn update(objects: &mut ManagedObjects, signal: &zbus::Message) -> zbus::Result<bool> {
+    let header = signal.header();
+    match header.member().map(|m| m.as_str()) {
+        Some("InterfacesAdded") => {
+            let (path, added): (OwnedObjectPath, ManagedInterfaces) =
+                signal.body().deserialize()?;
+            objects.entry(path).or_default().extend(added);
+        }
+        Some("InterfacesRemoved") => {
+            let (path, removed): (OwnedObjectPath, Vec<OwnedInterfaceName>) =
+                signal.body().deserialize()?;
+            if let Some(interfaces) = objects.get_mut(&path) {
+                for interface in &removed {
+                    interfaces.remove(interface);
+                }
+                if interfaces.is_empty() {
+                    objects.remove(&path);
+                }
+            }
+        }
+        Some("PropertiesChanged") => {
+            let (interface, changed, invalidated): (
+                OwnedInterfaceName,
+                HashMap<String, OwnedValue>,
+                Vec<String>,
+            ) = signal.body().deserialize()?;
+            if !invalidated.is_empty() {
+                return Ok(false);
+            }
+            let properties = header
+                .path()
+                .and_then(|path| objects.get_mut(&OwnedObjectPath::from(path.to_owned())))
+                .and_then(|interfaces| interfaces.get_mut(&interface));
+            if let Some(properties) = properties {
+                properties.extend(changed);
+            }
+        }
+        // BlueZ restarted.
+        Some("NameOwnerChanged") => return Ok(false),
+        _ => {}
+    }
+    Ok(true)
+}
+
+fn parse(objects: &ManagedObjects) -> Message {
+    let mut msg = Message::default();
+    for (path, interfaces) in objects {
+        let path = path.to_string();
+        let interfaces: HashMap<&str, _> =
+            interfaces.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        let property = |interface: &str, name: &str| interfaces.get(interface)?.get(name);
+        let string = |interface: &str, name: &str| {
+            property(interface, name)
+                .and_then(|v| v.downcast_ref::<&str>().ok())
+                .map(str::to_string)
+        };
+        let flag = |interface: &str, name: &str| {
+            property(interface, name)
+                .and_then(|v| v.downcast_ref::<bool>().ok())
+                .unwrap_or(false)
+        };
+
+        if interfaces.contains_key(ADAPTER_INTERFACE) {
+            msg.adapters.push(Adapter {
+                name: string(ADAPTER_INTERFACE, "Alias")
+                    .or_else(|| string(ADAPTER_INTERFACE, "Name"))
+                    .unwrap_or_else(|| path.clone()),
+                powered: flag(ADAPTER_INTERFACE, "Powered"),
+                path,
+            });
+        } else if interfaces.contains_key(DEVICE_INTERFACE) {
+            let address = string(DEVICE_INTERFACE, "Address").unwrap_or_default();
+            msg.devices.push(Device {
+                adapter: property(DEVICE_INTERFACE, "Adapter")
+                    .and_then(|v| v.downcast_ref::<ObjectPath>().ok())
+                    .map(|p| p.to_string())
+                    .unwrap_or_default(),
+                name: string(DEVICE_INTERFACE, "Alias").unwrap_or_else(|| address.clone()),
+                address,
+                paired: flag(DEVICE_INTERFACE, "Paired"),
+                connected: flag(DEVICE_INTERFACE, "Connected"),
+                battery: property(BATTERY_INTERFACE, "Percentage")
+                    .and_then(|v| v.downcast_ref::<u8>().ok()),
+                path,
+            });
+        }
+    }
+    msg.adapters.sort_by(|a, b| a.path.cmp(&b.path));
+    msg.devices
+        .sort_by(|a, b| (&a.name, &a.path).cmp(&(&b.name, &b.path)));
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::{fdo::ObjectManager, interface, object_server::SignalEmitter};
+
+    use super::*;
+    use crate::util::test_bus::TestBus;
+
+    struct FakeAdapter {
+        powered: bool,
+    }
+
+    #[interface(name = "org.bluez.Adapter1")]
+    impl FakeAdapter {
+        #[zbus(property)]
+        fn alias(&self) -> &str {
+            "laptop"
+        }
+
+        #[zbus(property)]
+        fn powered(&self) -> bool {
+            self.powered
+        }
+
+        #[zbus(property)]
+        fn set_powered(&mut self, powered: bool) {
+            self.powered = powered;
+        }
+    }
+
+    struct FakeDevice {
+        alias: &'static str,
+        connected: bool,
+    }
+
+    #[interface(name = "org.bluez.Device1")]
+    impl FakeDevice {
+        async fn connect(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+            self.connected = true;
+            self.connected_changed(&emitter).await.unwrap();
+        }
+
+        async fn disconnect(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) {
+            self.connected = false;
+            self.connected_changed(&emitter).await.unwrap();
+        }
+
+        #[zbus(property)]
+        fn alias(&self) -> &str {
+            self.alias
+        }
+
+        #[zbus(property)]
+        fn address(&self) -> &str {
+            "00:11:22:33:44:55"
+        }
+
+        #[zbus(property)]
+        fn adapter(&self) -> ObjectPath<'_> {
+            ObjectPath::from_static_str_unchecked("/org/bluez/hci0")
+        }
+
+        #[zbus(property)]
+        fn paired(&self) -> bool {
+            true
+        }
+
+        #[zbus(property)]
+        fn connected(&self) -> bool {
+            self.connected
+        }
+    }
+
+    struct FakeBattery;
+
+    #[interface(name = "org.bluez.Battery1")]
+    impl FakeBattery {
+        #[zbus(property)]
+        fn percentage(&self) -> u8 {
+            80
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_fake_bluez() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+
+        const HEADPHONES: &str = "/org/bluez/hci0/dev_00_11_22_33_44_55";
+        let service = bus
+            .builder()
+            .name(SERVICE)
+            .unwrap()
+            .serve_at("/", ObjectManager)
+            .unwrap()
+            .serve_at("/org/bluez/hci0", FakeAdapter { powered: true })
+            .unwrap()
+            .serve_at(
+                HEADPHONES,
+                FakeDevice {
+                    alias: "Headphones",
+                    connected: false,
+                },
+            )
+            .unwrap()
+            .serve_at(HEADPHONES, FakeBattery)
+            .unwrap()
+            .serve_at(
+                "/org/bluez/hci0/dev_66_77_88_99_AA_BB",
+                FakeDevice {
+                    alias: "Keyboard",
+                    connected: true,
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let client = bus.connect().await;
+        let (sender, mut receiver) = watch::channel(Message::default());
+        let c = client.clone();
+        tokio::spawn(async move { run(&c, &sender).await });
+
+        let mut next = async || {
+            tokio::time::timeout(Duration::from_secs(5), receiver.changed())
+                .await
+                .expect("bluetooth change was not published")
+                .unwrap();
+            receiver.borrow_and_update().clone()
+        };
+
+        let msg = next().await;
+        assert_eq!(msg.adapters.len(), 1);
+        assert_eq!(msg.adapters[0].name, "laptop");
+        assert!(msg.adapters[0].powered);
+        let devices: Vec<_> = msg.devices_of(&msg.adapters[0]).collect();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].name, "Headphones");
+        assert_eq!(devices[0].battery, Some(80));
+        assert!(!devices[0].connected);
+        assert_eq!(devices[1].name, "Keyboard");
+        assert_eq!(devices[1].battery, None);
+        assert!(devices[1].connected);
+
+        apply(&client, &Command::Connect(HEADPHONES.into()))
+            .await
+            .unwrap();
+        assert!(next().await.devices[0].connected);
+
+        apply(
+            &client,
+            &Command::SetPowered("/org/bluez/hci0".into(), false),
+        )
+        .await
+        .unwrap();
+        assert!(!next().await.adapters[0].powered);
+
+        // Devices come and go as they're discovered.
+        const SPEAKER: &str = "/org/bluez/hci0/dev_CC_DD_EE_FF_00_11";
+        let speaker = FakeDevice {
+            alias: "Speaker",
+            connected: false,
+        };
+        service.object_server().at(SPEAKER, speaker).await.unwrap();
+        let names: Vec<_> = next().await.devices.into_iter().map(|d| d.name).collect();
+        assert_eq!(names, ["Headphones", "Keyboard", "Speaker"]);
+
+        service
+            .object_server()
+            .remove::<FakeDevice, _>(SPEAKER)
+            .await
+            .unwrap();
+        assert_eq!(next().await.devices.len(), 2);
+    }
+}
@@ -0,0 +1,251 @@
+//! Block device throughput, from the cumulative counters in
+//! `/proc/diskstats`.
+
+use std::{collections::HashMap, ffi::c_ulong, time::Duration};
+
+/// The kernel counts sectors in 512-byte units, whatever the device's actual
+/// sector size.
+const SECTOR_SIZE: u64 = 512;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DiskIo {
+    pub name: String,
+    /// Bytes per second read since the last tick.
+    pub read: u64,
+    /// Bytes per second written since the last tick.
+    pub written: u64,
+    /// Whether this is a whole device, as opposed to a partition.
+    pub whole: bool,
+}
+
+/// Turns the counters into rates. Remembers each device's counters from the
+/// previous read.
+pub struct Reader {
+    previous: HashMap<String, Counters>,
+    /// The width of the kernel's counters, which are `unsigned long`.
+    counter_bits: u32,
+}
+
+impl Default for Reader {
+    fn default() -> Self {
+        Self {
+            previous: HashMap::new(),
+            counter_bits: c_ulong::BITS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Counters {
+    sectors_read: u64,
+    sectors_written: u64,
+}
+
+impl Reader {
+    /// Every block device, sorted by name. `elapsed` is the time since the
+    /// previous read.
+    pub fn read(&mut self, elapsed: Duration) -> Vec<DiskIo> {
+        let contents = std::fs::read_to_string("/proc/diskstats").unwrap_or_default();
+        let mut disks = self.rates(&contents, elapsed);
+        for disk in &mut disks {
+            // Partitions don't get an entry of their own in /sys/block.
+            let sys_name = disk.name.replace('/', "!");
+            disk.whole = std::path::Path::new(&format!("/sys/block/{sys_name}")).exists();
+        }
+        disks
+    }
+
+    fn rates(&mut self, contents: &str, elapsed: Duration) -> Vec<DiskIo> {
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let rate = |sectors: u64| (sectors as f64 * SECTOR_SIZE as f64 / secs) as u64;
+
+        let current = parse_diskstats(contents);
+        let mut disks: Vec<DiskIo> = current
+            .iter()
+            .map(|(name, counters)| {
+                // A device that just appeared has nothing to compare against.
+                let (read, written) = match self.previous.get(name) {
+                    Some(previous) => (
+                        delta(
+                            previous.sectors_read,
+                            counters.sectors_read,
+                            self.counter_bits,
+                        ),
+                        delta(
+                            previous.sectors_written,
+                            counters.sectors_written,
+                            self.counter_bits,
+                        ),
+                    ),
+                    None => (0, 0),
+                };
+                DiskIo {
+                    name: name.clone(),
+                    read: rate(read),
+                    written: rate(written),
+                    whole: false,
+                }
+            })
+            .collect();
+        disks.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // Replacing the map forgets devices that have disappeared.
+        self.previous = current;
+        disks
+    }
+}
+
+/// How far a counter `bits` wide advanced. A counter that went backwards from
+/// the upper half of its range wrapped. Otherwise it was reset (e.g. the
+/// device was replaced), and counts as idle.
+fn delta(previous: u64, current: u64, bits: u32) -> u64 {
+    let max = u64::MAX >> (u64::BITS - bits);
+    if current >= previous {
+        current - previous
+    } else if previous <= max && previous > max / 2 {
+        (max - previous) + current + 1
+    } else {
+        0
+    }
+}
+
+fn parse_diskstats(contents: &str) -> HashMap<String, Counters> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            // Fields: major, minor, name, reads completed, reads merged,
+            // sectors read, time reading, writes completed, writes merged,
+            // sectors written, ...
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = fields.get(2)?;
+            let sectors_read = fields.get(5)?.parse().ok()?;
+            let sectors_written = fields.get(9)?.parse().ok()?;
+            Some((
+                name.to_string(),
+                Counters {
+                    sectors_read,
+                    sectors_written,
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEFORE: &str = "\
+   7       0 loop0 12 0 2048 3 0 0 0 0 0 8 3 0 0 0 0 0 0
+ 259       0 nvme0n1 1000 10 80000 500 2000 20 160000 900 0 1200 1400 0 0 0 0 10 5
+ 259       1 nvme0n1p1 100 0 4000 50 0 0 0 0 0 60 50 0 0 0 0 0 0
+   8       0 sda 4294967000 0 4294967000 0 1 0 4294967290 0 0 0 0
+";
+
+    fn reader(contents: &str) -> Reader {
+        let mut reader = Reader::default();
+        reader.rates(contents, Duration::from_secs(1));
+        reader
+    }
+
+    fn find<'a>(disks: &'a [DiskIo], name: &str) -> Option<&'a DiskIo> {
+        disks.iter().find(|d| d.name == name)
+    }
+
+    #[test]
+    fn parses_counters() {
+        let counters = parse_diskstats(BEFORE);
+        assert_eq!(counters.len(), 4);
+        assert_eq!(
+            counters["nvme0n1"],
+            Counters {
+                sectors_read: 80000,
+                sectors_written: 160000,
+            }
+        );
+    }
+
+    #[test]
+    fn rates_over_elapsed_time() {
+        let mut reader = reader(BEFORE);
+        let after = BEFORE.replace("80000 500 2000 20 160000", "80200 500 2000 20 160400");
+        let disks = reader.rates(&after, Duration::from_secs(2));
+
+        let nvme = find(&disks, "nvme0n1").unwrap();
+        assert_eq!(nvme.read, 100 * SECTOR_SIZE);
+        assert_eq!(nvme.written, 200 * SECTOR_SIZE);
+        assert_eq!(find(&disks, "loop0").unwrap().read, 0);
+    }
+
+    /// sda's counters pass 2^32 and come back small.
+    fn wrapped() -> String {
+        let after = BEFORE.replace("4294967000 0 4294967000", "4294967000 0 100");
+        after.replace("4294967290", "10")
+    }
+
+    #[test]
+    fn counter_wraparound() {
+        let mut reader = Reader {
+            counter_bits: 32,
+            ..Reader::default()
+        };
+        reader.rates(BEFORE, Duration::from_secs(1));
+        let disks = reader.rates(&wrapped(), Duration::from_secs(1));
+
+        let sda = find(&disks, "sda").unwrap();
+        assert_eq!(sda.read, (4294967296 - 4294967000 + 100) * SECTOR_SIZE);
+        assert_eq!(sda.written, 16 * SECTOR_SIZE);
+    }
+
+    /// 64-bit counters don't wrap at 2^32, so going backwards is a reset.
+    #[test]
+    fn counter_reset() {
+        let mut reader = Reader {
+            counter_bits: 64,
+            ..Reader::default()
+        };
+        reader.rates(BEFORE, Duration::from_secs(1));
+        let disks = reader.rates(&wrapped(), Duration::from_secs(1));
+
+        let sda = find(&disks, "sda").unwrap();
+        assert_eq!(sda.read, 0);
+        assert_eq!(sda.written, 0);
+    }
+
+    #[test]
+    fn counter_deltas() {
+        assert_eq!(delta(u32::MAX as u64 - 10, 5, 32), 16);
+        assert_eq!(delta(u64::MAX - 10, 5, 64), 16);
+        assert_eq!(delta(1 << 40, 5, 64), 0);
+        // Resets, from the lower half of the range or beyond it.
+        assert_eq!(delta(1000, 5, 32), 0);
+        assert_eq!(delta(1 << 40, 5, 32), 0);
+    }
+
+    #[test]
+    fn devices_appearing_and_disappearing() {
+        let mut reader = reader(BEFORE);
+
+        // sdb is plugged in with counters it accumulated elsewhere, and the
+        // loop device goes away.
+        let mut after: String = BEFORE
+            .lines()
+            .filter(|line| !line.contains("loop0"))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        after.push_str("   8      16 sdb 50 0 9000 10 0 0 0 0 0 10 10\n");
+        let disks = reader.rates(&after, Duration::from_secs(1));
+        assert!(find(&disks, "loop0").is_none());
+        assert_eq!(find(&disks, "sdb").unwrap().read, 0);
+
+        // Once seen, sdb is measured as usual. The loop device comes back
+        // with fresh counters, and is treated as new rather than as a wrap.
+        let later = format!(
+            "{}   7       0 loop0 1 0 8 0 0 0 0 0 0 0 0\n",
+            after.replace("sdb 50 0 9000", "sdb 50 0 9010")
+        );
+        let disks = reader.rates(&later, Duration::from_secs(1));
+        assert_eq!(find(&disks, "sdb").unwrap().read, 10 * SECTOR_SIZE);
+        assert_eq!(find(&disks, "loop0").unwrap().read, 0);
+    }
+}
@@ -0,0 +1,434 @@
+//! Whether a notification daemon is in do-not-disturb mode, followed through
+//! a [`Backend`] for each daemon we know how to talk to.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{StreamExt, stream::BoxStream};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    process::Command as Process,
+    sync::{OnceCell, watch},
+};
+use zbus::{Connection, MatchRule, MessageStream, message, proxy::CacheProperties};
+
+use crate::producer::{notifications, publish};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How often to ask mako, which has no way to tell us about changes.
+const MAKO_POLL: Duration = Duration::from_secs(2);
+const MAKO_MODE: &str = "do-not-disturb";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    /// `None` until the daemon has been reached.
+    pub paused: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Daemon {
+    /// Our own, which a notifications module must be configured to run.
+    /// Without one, this is never reached.
+    #[default]
+    Rustybar,
+    /// Through `makoctl`, with a "do-not-disturb" mode, which mako's config
+    /// should define.
+    Mako,
+    Swaync,
+    Dunst,
+}
+
+/// Something that can pause a notification daemon.
+#[async_trait]
+pub trait Backend: Send + Sync + 'static {
+    async fn paused(&self) -> eyre::Result<bool>;
+
+    /// Yields whenever the paused state may have changed.
+    async fn events(&self) -> eyre::Result<BoxStream<'static, ()>>;
+
+    async fn set_paused(&self, paused: bool) -> eyre::Result<()>;
+}
+
+async fn backend(daemon: Daemon) -> eyre::Result<Box<dyn Backend>> {
+    Ok(match daemon {
+        Daemon::Rustybar => Box::new(Rustybar),
+        Daemon::Mako => Box::new(Mako),
+        Daemon::Swaync => Box::new(Swaync(session().await?.clone())),
+        Daemon::Dunst => Box::new(Dunst(session().await?.clone())),
+    })
+}
+
+async fn session() -> zbus::Result<&'static Connection> {
+    static SESSION: OnceCell<Connection> = OnceCell::const_new();
+    SESSION.get_or_try_init(Connection::session).await
+}
+
+pub fn listen(daemon: Daemon) -> watch::Receiver<Message> {
+    static SENDERS: LazyLock<Mutex<HashMap<Daemon, watch::Sender<Message>>>> =
+        LazyLock::new(Default::default);
+
+    SENDERS
+        .lock()
+        .unwrap()
+        .entry(daemon)
+        .or_insert_with(|| {
+            let (sender, _) = watch::channel(Message::default());
+
+            let s = sender.clone();
+
+            tokio::spawn(async move {
+                // The last error logged, so that a daemon that stays
+                // unreachable isn't reported every few seconds.
+                let mut last_error = None;
+                loop {
+                    let result = match backend(daemon).await {
+                        Ok(backend) => follow(backend.as_ref(), &sender).await,
+                        Err(e) => Err(e),
+                    };
+                    if sender.borrow().paused.is_some() {
+                        last_error = None;
+                    }
+                    if let Err(e) = result {
+                        let e = e.to_string();
+                        if last_error.as_ref() != Some(&e) {
+                            eprintln!("dnd: lost {daemon:?}, reconnecting: {e}");
+                        }
+                        last_error = Some(e);
+                    }
+                    publish(&sender, Message::default());
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            });
+            s
+        })
+        .subscribe()
+}
+
+/// Pauses the daemon if it isn't, or unpauses it if it is.
+pub async fn toggle(daemon: Daemon) {
+    let result = async {
+        let backend = backend(daemon).await?;
+        let paused = backend.paused().await?;
+        backend.set_paused(!paused).await
+    }
+    .await;
+    if let Err(e) = result {
+        eprintln!("dnd: couldn't toggle {daemon:?}: {e}");
+    }
+}
+
+/// Publishes whether the daemon is paused, then again after every event.
+async fn follow(backend: &dyn Backend, sender: &watch::Sender<Message>) -> eyre::Result<()> {
+    // Subscribe first, so nothing between the read and the subscription is
+    // missed.
+    let mut events = backend.events().await?;
+    loop {
+        let paused = backend.paused().await?;
+        // Most events aren't about pausing, so don't wake the bars for them.
+        if sender.borrow().paused != Some(paused) {
+            publish(
+                sender,
+                Message {
+                    paused: Some(paused),
+                },
+            );
+        }
+        if events.next().await.is_none() {
+            eyre::bail!("event stream ended");
+        }
+    }
+}
+
+/// Only follows our daemon, which is up to the notifications module to start.
+struct Rustybar;
+
+fn rustybar() -> eyre::Result<watch::Receiver<notifications::Message>> {
+    notifications::running().ok_or_else(|| eyre::eyre!("the notifications module isn't running"))
+}
+
+#[async_trait]
+impl Backend for Rustybar {
+    async fn paused(&self) -> eyre::Result<bool> {
+        Ok(rustybar()?.borrow().do_not_disturb)
+    }
+
+    async fn events(&self) -> eyre::Result<BoxStream<'static, ()>> {
+        let mut receiver = rustybar()?;
+        Ok(Box::pin(async_stream::stream! {
+            while receiver.changed().await.is_ok() {
+                yield ();
+            }
+        }))
+    }
+
+    async fn set_paused(&self, paused: bool) -> eyre::Result<()> {
+        if rustybar()?.borrow().do_not_disturb != paused {
+            notifications::command(notifications::Command::ToggleDoNotDisturb).await;
+        }
+        Ok(())
+    }
+}
+
+struct Mako;
+
+async fn makoctl(args: &[&str]) -> eyre::Result<String> {
+    let output = Process::new("makoctl").args(args).output().await?;
+    if !output.status.success() {
+        eyre::bail!(
+            "makoctl {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Whether `makoctl mode`, which lists the active modes, includes ours.
+fn parse_modes(modes: &str) -> bool {
+    modes.lines().any(|mode| mode.trim() == MAKO_MODE)
+}
+
+#[async_trait]
+impl Backend for Mako {
+    async fn paused(&self) -> eyre::Result<bool> {
+        Ok(parse_modes(&makoctl(&["mode"]).await?))
+    }
+
+    async fn events(&self) -> eyre::Result<BoxStream<'static, ()>> {
+        Ok(Box::pin(async_stream::stream! {
+            loop {
+                tokio::time::sleep(MAKO_POLL).await;
+                yield ();
+            }
+        }))
+    }
+
+    async fn set_paused(&self, paused: bool) -> eyre::Result<()> {
+        let flag = if paused { "-a" } else { "-r" };
+        makoctl(&["mode", flag, MAKO_MODE]).await?;
+        Ok(())
+    }
+}
+
+#[zbus::proxy(
+    interface = "org.erikreider.swaync.cc",
+    default_service = "org.erikreider.swaync.cc",
+    default_path = "/org/erikreider/swaync/cc"
+)]
+trait SwayncControlCenter {
+    fn get_dnd(&self) -> zbus::Result<bool>;
+    fn set_dnd(&self, state: bool) -> zbus::Result<()>;
+}
+
+struct Swaync(Connection);
+
+#[async_trait]
+impl Backend for Swaync {
+    async fn paused(&self) -> eyre::Result<bool> {
+        Ok(SwayncControlCenterProxy::new(&self.0)
+            .await?
+            .get_dnd()
+            .await?)
+    }
+
+    async fn events(&self) -> eyre::Result<BoxStream<'static, ()>> {
+        // swaync sends its state, including do-not-disturb, to subscribers
+        // whenever it changes.
+        let rule = MatchRule::builder()
+            .msg_type(message::Type::Signal)
+            .interface("org.erikreider.swaync.cc")?
+            .build();
+        let signals = MessageStream::for_match_rule(rule, &self.0, None).await?;
+        Ok(Box::pin(signals.map(|_| ())))
+    }
+
+    async fn set_paused(&self, paused: bool) -> eyre::Result<()> {
+        SwayncControlCenterProxy::new(&self.0)
+            .await?
+            .set_dnd(paused)
+            .await?;
+        Ok(())
+    }
+}
+
+#[zbus::proxy(
+    interface = "org.dunstproject.cmd0",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait DunstControl {
+    #[zbus(property)]
+    fn paused(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_paused(&self, paused: bool) -> zbus::Result<()>;
+}
+
+struct Dunst(Connection);
+
+impl Dunst {
+    async fn proxy(&self) -> zbus::Result<DunstControlProxy<'_>> {
+        DunstControlProxy::builder(&self.0)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+    }
+}
+
+#[async_trait]
+impl Backend for Dunst {
+    async fn paused(&self) -> eyre::Result<bool> {
+        Ok(self.proxy().await?.paused().await?)
+    }
+
+    async fn events(&self) -> eyre::Result<BoxStream<'static, ()>> {
+        let rule = MatchRule::builder()
+            .msg_type(message::Type::Signal)
+            .path("/org/freedesktop/Notifications")?
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .arg(0, "org.dunstproject.cmd0")?
+            .build();
+        let signals = MessageStream::for_match_rule(rule, &self.0, None).await?;
+        Ok(Box::pin(signals.map(|_| ())))
+    }
+
+    async fn set_paused(&self, paused: bool) -> eyre::Result<()> {
+        self.proxy().await?.set_paused(paused).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::util::test_bus::TestBus;
+
+    #[test]
+    fn parses_mako_modes() {
+        assert!(!parse_modes("default\n"));
+        assert!(parse_modes("default\ndo-not-disturb\n"));
+        assert!(!parse_modes("do-not-disturb-later\n"));
+    }
+
+    #[tokio::test]
+    async fn rustybar_only_follows_a_running_daemon() {
+        assert!(Rustybar.paused().await.is_err());
+        assert!(Rustybar.events().await.is_err());
+        assert!(notifications::running().is_none());
+    }
+
+    /// A daemon in memory, which sends an event whenever it's paused or
+    /// unpaused.
+    struct FakeBackend {
+        paused: Mutex<bool>,
+        events: Mutex<Option<mpsc::UnboundedReceiver<()>>>,
+        notify: mpsc::UnboundedSender<()>,
+    }
+
+    #[async_trait]
+    impl Backend for FakeBackend {
+        async fn paused(&self) -> eyre::Result<bool> {
+            Ok(*self.paused.lock().unwrap())
+        }
+
+        async fn events(&self) -> eyre::Result<BoxStream<'static, ()>> {
+            let mut events = self.events.lock().unwrap().take().unwrap();
+            Ok(Box::pin(async_stream::stream! {
+                while let Some(()) = events.recv().await {
+                    yield ();
+                }
+            }))
+        }
+
+        async fn set_paused(&self, paused: bool) -> eyre::Result<()> {
+            *self.paused.lock().unwrap() = paused;
+            self.notify.send(()).unwrap();
+            Ok(())
+        }
+    }
+
+    async fn paused(receiver: &mut watch::Receiver<Message>) -> Option<bool> {
+        tokio::time::timeout(Duration::from_secs(5), receiver.changed())
+            .await
+            .expect("pausing was not published")
+            .unwrap();
+        receiver.borrow_and_update().paused
+    }
+
+    #[tokio::test]
+    async fn follows_pausing() {
+        let (notify, events) = mpsc::unbounded_channel();
+        let backend = Arc::new(FakeBackend {
+            paused: Mutex::new(false),
+            events: Mutex::new(Some(events)),
+            notify: notify.clone(),
+        });
+        let (sender, mut receiver) = watch::channel(Message::default());
+        let b = backend.clone();
+        tokio::spawn(async move { follow(b.as_ref(), &sender).await });
+
+        assert_eq!(paused(&mut receiver).await, Some(false));
+        backend.set_paused(true).await.unwrap();
+        assert_eq!(paused(&mut receiver).await, Some(true));
+
+        // Events that don't change anything aren't published.
+        notify.send(()).unwrap();
+        backend.set_paused(false).await.unwrap();
+        assert_eq!(paused(&mut receiver).await, Some(false));
+    }
+
+    struct FakeDunst {
+        paused: bool,
+    }
+
+    #[zbus::interface(name = "org.dunstproject.cmd0")]
+    impl FakeDunst {
+        #[zbus(property)]
+        fn paused(&self) -> bool {
+            self.paused
+        }
+
+        #[zbus(property)]
+        fn set_paused(&mut self, paused: bool) {
+            self.paused = paused;
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_fake_dunst() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+        let _service = bus
+            .builder()
+            .name("org.freedesktop.Notifications")
+            .unwrap()
+            .serve_at(
+                "/org/freedesktop/Notifications",
+                FakeDunst { paused: false },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let dunst = Arc::new(Dunst(bus.connect().await));
+        let (sender, mut receiver) = watch::channel(Message::default());
+        let d = dunst.clone();
+        tokio::spawn(async move { follow(d.as_ref(), &sender).await });
+
+        assert_eq!(paused(&mut receiver).await, Some(false));
+        dunst.set_paused(true).await.unwrap();
+        assert_eq!(paused(&mut receiver).await, Some(true));
+    }
+}
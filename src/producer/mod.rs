@@ -1,5 +1,39 @@
+use std::sync::LazyLock;
+
+use tokio::sync::watch;
+
+pub mod audio;
+pub mod backlight;
+pub mod bluetooth;
+pub mod diskstats;
+pub mod dnd;
+pub mod mpris;
+pub mod networkmanager;
 pub mod niri;
+pub mod notifications;
 pub mod tick;
+pub mod tray;
+pub mod upower;
+pub mod wifi;
+
+static UPDATES: LazyLock<watch::Sender<()>> = LazyLock::new(|| watch::channel(()).0);
+
+/// Changes whenever an event-driven producer publishes, so bars can redraw
+/// right away instead of on the next tick.
+pub fn updates() -> watch::Receiver<()> {
+    UPDATES.subscribe()
+}
+
+/// Sends `msg` to the producer's receivers, and wakes the bars.
+pub(crate) fn publish<T>(sender: &watch::Sender<T>, msg: T) {
+    sender.send_replace(msg);
+    wake();
+}
+
+/// Wakes the bars, for changes that don't come through a producer.
+pub(crate) fn wake() {
+    UPDATES.send_replace(());
+}
 
 // pub trait Producer {
 //     fn produce(&mut self) -> BoxFuture<'_, Message>;
@@ -0,0 +1,365 @@
+//! Media players that implement MPRIS on the session bus.
+
+use std::{collections::HashMap, sync::LazyLock, time::Duration};
+
+use futures::{StreamExt, stream};
+use tokio::sync::{OnceCell, watch};
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::{DBusProxy, PropertiesProxy},
+    message,
+    names::InterfaceName,
+    zvariant::OwnedValue,
+};
+
+use crate::producer::publish;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const PREFIX: &str = "org.mpris.MediaPlayer2.";
+const PATH: &str = "/org/mpris/MediaPlayer2";
+const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+#[derive(Debug, Default)]
+pub struct Message {
+    /// Every player, sorted by bus name.
+    pub players: Vec<Player>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Player {
+    /// The player's bus name, e.g. "org.mpris.MediaPlayer2.spotify".
+    pub bus_name: String,
+    /// The player's own name for itself, e.g. "Spotify".
+    pub identity: Option<String>,
+    pub status: PlaybackStatus,
+    pub artists: Vec<String>,
+    pub title: Option<String>,
+}
+
+impl Player {
+    /// The bus name without the MPRIS prefix, e.g. "spotify" or
+    /// "firefox.instance_1_84".
+    pub fn short_name(&self) -> &str {
+        self.bus_name.strip_prefix(PREFIX).unwrap_or(&self.bus_name)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    PlayPause,
+    Next,
+    Previous,
+}
+
+#[zbus::proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait MediaPlayer {
+    fn play_pause(&self) -> zbus::Result<()>;
+    fn next(&self) -> zbus::Result<()>;
+    fn previous(&self) -> zbus::Result<()>;
+}
+
+pub fn listen() -> watch::Receiver<Message> {
+    static SENDER: LazyLock<watch::Sender<Message>> = LazyLock::new(|| {
+        let (sender, _) = watch::channel(Message::default());
+
+        let s = sender.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let result = match session().await {
+                    Ok(connection) => run(connection, &sender).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("mpris: connection ended, reconnecting: {e}");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        s
+    });
+
+    SENDER.subscribe()
+}
+
+async fn session() -> zbus::Result<&'static Connection> {
+    static SESSION: OnceCell<Connection> = OnceCell::const_new();
+    SESSION.get_or_try_init(Connection::session).await
+}
+
+/// Sends `command` to the player with this bus name.
+pub async fn command(bus_name: String, command: Command) {
+    let result = async {
+        let player = MediaPlayerProxy::builder(session().await?)
+            .destination(bus_name.as_str())?
+            .build()
+            .await?;
+        match command {
+            Command::PlayPause => player.play_pause().await,
+            Command::Next => player.next().await,
+            Command::Previous => player.previous().await,
+        }
+    }
+    .await;
+    if let Err(e) = result {
+        eprintln!("mpris: {command:?} failed for {bus_name}: {e}");
+    }
+}
+
+/// Publishes the players, then again whenever one of them changes, appears or
+/// goes away.
+async fn run(connection: &Connection, sender: &watch::Sender<Message>) -> zbus::Result<()> {
+    let changes = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .path(PATH)?
+        .build();
+    let owners = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg0ns("org.mpris.MediaPlayer2")?
+        .build();
+    let mut signals = stream::select(
+        MessageStream::for_match_rule(changes, connection, None).await?,
+        MessageStream::for_match_rule(owners, connection, None).await?,
+    );
+
+    loop {
+        publish(
+            sender,
+            Message {
+                players: read(connection).await?,
+            },
+        );
+        if signals.next().await.is_none() {
+            return Ok(());
+        }
+    }
+}
+
+async fn read(connection: &Connection) -> zbus::Result<Vec<Player>> {
+    let mut names: Vec<String> = DBusProxy::new(connection)
+        .await?
+        .list_names()
+        .await?
+        .into_iter()
+        .map(|name| name.to_string())
+        .filter(|name| name.starts_with(PREFIX))
+        .collect();
+    names.sort();
+
+    let mut players = Vec::new();
+    for bus_name in names {
+        let properties = PropertiesProxy::builder(connection)
+            .destination(bus_name.as_str())?
+            .path(PATH)?
+            .build()
+            .await?;
+        // Players come and go; one vanishing mid-read isn't an error.
+        let Ok(player) = properties
+            .get_all(InterfaceName::from_static_str_unchecked(PLAYER_INTERFACE))
+            .await
+        else {
+            continue;
+        };
+        let identity = properties
+            .get(
+                InterfaceName::from_static_str_unchecked(ROOT_INTERFACE),
+                "Identity",
+            )
+            .await
+            .ok()
+            .and_then(|v| String::try_from(v).ok());
+        players.push(parse_player(bus_name, identity, &player));
+    }
+    Ok(players)
+}
+
+fn parse_player(
+    bus_name: String,
+    identity: Option<String>,
+    properties: &HashMap<String, OwnedValue>,
+) -> Player {
+    let status = match properties
+        .get("PlaybackStatus")
+        .and_then(|v| v.downcast_ref::<&str>().ok())
+    {
+        Some("Playing") => PlaybackStatus::Playing,
+        Some("Paused") => PlaybackStatus::Paused,
+        _ => PlaybackStatus::Stopped,
+    };
+    let metadata: HashMap<String, OwnedValue> = properties
+        .get("Metadata")
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| v.try_into().ok())
+        .unwrap_or_default();
+    let artists = metadata
+        .get("xesam:artist")
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| Vec::<String>::try_from(v).ok())
+        .unwrap_or_default();
+    let title = metadata
+        .get("xesam:title")
+        .and_then(|v| v.downcast_ref::<&str>().ok())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string);
+
+    Player {
+        bus_name,
+        identity,
+        status,
+        artists,
+        title,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::{interface, zvariant::Value};
+
+    use super::*;
+    use crate::util::test_bus::TestBus;
+
+    struct FakeRoot {
+        identity: String,
+    }
+
+    #[interface(name = "org.mpris.MediaPlayer2")]
+    impl FakeRoot {
+        #[zbus(property)]
+        fn identity(&self) -> String {
+            self.identity.clone()
+        }
+    }
+
+    struct FakePlayer {
+        playing: bool,
+        track: usize,
+    }
+
+    const TRACKS: [(&str, &str); 2] = [("Artist A", "First"), ("Artist B", "Second")];
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        async fn play_pause(
+            &mut self,
+            #[zbus(signal_emitter)] emitter: zbus::object_server::SignalEmitter<'_>,
+        ) {
+            self.playing = !self.playing;
+            self.playback_status_changed(&emitter).await.unwrap();
+        }
+
+        async fn next(
+            &mut self,
+            #[zbus(signal_emitter)] emitter: zbus::object_server::SignalEmitter<'_>,
+        ) {
+            self.track = (self.track + 1) % TRACKS.len();
+            self.metadata_changed(&emitter).await.unwrap();
+        }
+
+        fn previous(&mut self) {}
+
+        #[zbus(property)]
+        fn playback_status(&self) -> &str {
+            if self.playing { "Playing" } else { "Paused" }
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let (artist, title) = TRACKS[self.track];
+            HashMap::from([
+                (
+                    "xesam:artist".to_string(),
+                    Value::from(vec![artist]).try_into().unwrap(),
+                ),
+                (
+                    "xesam:title".to_string(),
+                    Value::from(title).try_into().unwrap(),
+                ),
+            ])
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_fake_player() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+
+        let _service = bus
+            .builder()
+            .name("org.mpris.MediaPlayer2.fake")
+            .unwrap()
+            .serve_at(
+                PATH,
+                FakeRoot {
+                    identity: "Fake Player".into(),
+                },
+            )
+            .unwrap()
+            .serve_at(
+                PATH,
+                FakePlayer {
+                    playing: false,
+                    track: 0,
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let client = bus.connect().await;
+        let (sender, mut receiver) = watch::channel(Message::default());
+        let c = client.clone();
+        tokio::spawn(async move { run(&c, &sender).await });
+
+        let mut next = async |check: fn(&Player) -> bool| {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    receiver.changed().await.unwrap();
+                    let msg = receiver.borrow_and_update();
+                    if let Some(player) = msg.players.first()
+                        && check(player)
+                    {
+                        return player.clone();
+                    }
+                }
+            })
+            .await
+            .expect("player change was not published")
+        };
+
+        let player = next(|_| true).await;
+        assert_eq!(player.short_name(), "fake");
+        assert_eq!(player.identity.as_deref(), Some("Fake Player"));
+        assert_eq!(player.status, PlaybackStatus::Paused);
+        assert_eq!(player.artists, ["Artist A"]);
+        assert_eq!(player.title.as_deref(), Some("First"));
+
+        let player = MediaPlayerProxy::builder(&client)
+            .destination("org.mpris.MediaPlayer2.fake")
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        player.play_pause().await.unwrap();
+        next(|p| p.status == PlaybackStatus::Playing).await;
+        player.next().await.unwrap();
+        let changed = next(|p| p.title.as_deref() == Some("Second")).await;
+        assert_eq!(changed.artists, ["Artist B"]);
+    }
+}
@@ -0,0 +1,751 @@
+//! Connections from NetworkManager on the system bus: which are active, and
+//! which are saved and could be switched to.
+
+use std::{collections::HashMap, sync::LazyLock, time::Duration};
+
+use futures::{StreamExt, stream};
+use tokio::sync::{OnceCell, watch};
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::PropertiesProxy,
+    message,
+    names::InterfaceName,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+};
+
+use crate::producer::publish;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const SERVICE: &str = "org.freedesktop.NetworkManager";
+const MANAGER_PATH: &str = "/org/freedesktop/NetworkManager";
+const ACTIVE_INTERFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const ACCESS_POINT_INTERFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+const SETTINGS_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings";
+const SETTINGS_CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Message {
+    /// `None` until NetworkManager has been reached.
+    pub network: Option<Network>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Network {
+    /// The primary connection first, then the rest by name.
+    pub active: Vec<ActiveConnection>,
+    /// Saved connections that can be switched to, by name.
+    pub saved: Vec<SavedConnection>,
+}
+
+impl Network {
+    /// The connection that carries the default route, if any.
+    pub fn primary(&self) -> Option<&ActiveConnection> {
+        self.active.first().filter(|c| c.primary)
+    }
+
+    pub fn vpn(&self) -> Option<&ActiveConnection> {
+        self.active.iter().find(|c| c.kind.is_vpn())
+    }
+
+    /// The active connection for a saved one, if it's active.
+    pub fn activation(&self, saved: &SavedConnection) -> Option<&ActiveConnection> {
+        self.active.iter().find(|a| a.settings == saved.path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveConnection {
+    /// The active connection's object path, for deactivating it.
+    pub path: String,
+    /// The path of its saved settings.
+    pub settings: String,
+    pub name: String,
+    pub kind: ConnectionKind,
+    pub activated: bool,
+    pub primary: bool,
+    /// Signal strength of the access point, from 0 to 100, for wifi.
+    pub strength: Option<u8>,
+    /// The path of the access point, for wifi.
+    pub access_point: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedConnection {
+    pub path: String,
+    pub name: String,
+    pub kind: ConnectionKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionKind {
+    Wifi,
+    Ethernet,
+    Vpn,
+    Wireguard,
+    /// Bridges, loopback, and the like.
+    Other,
+}
+
+impl ConnectionKind {
+    /// From NetworkManager's connection type, e.g. "802-11-wireless".
+    fn from_nm(kind: &str) -> Self {
+        match kind {
+            "802-11-wireless" => ConnectionKind::Wifi,
+            "802-3-ethernet" => ConnectionKind::Ethernet,
+            "vpn" => ConnectionKind::Vpn,
+            "wireguard" => ConnectionKind::Wireguard,
+            _ => ConnectionKind::Other,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ConnectionKind::Wifi => "wifi",
+            ConnectionKind::Ethernet => "ethernet",
+            ConnectionKind::Vpn => "vpn",
+            ConnectionKind::Wireguard => "wireguard",
+            ConnectionKind::Other => "other",
+        }
+    }
+
+    pub fn is_vpn(self) -> bool {
+        matches!(self, ConnectionKind::Vpn | ConnectionKind::Wireguard)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Activates the saved connection at this path.
+    Activate(String),
+    /// Deactivates the active connection at this path.
+    Deactivate(String),
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait NetworkManager {
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    #[zbus(property)]
+    fn primary_connection(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+trait Settings {
+    fn list_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait SettingsConnection {
+    fn get_settings(&self) -> zbus::Result<HashMap<String, HashMap<String, OwnedValue>>>;
+}
+
+pub fn listen() -> watch::Receiver<Message> {
+    static SENDER: LazyLock<watch::Sender<Message>> = LazyLock::new(|| {
+        let (sender, _) = watch::channel(Message::default());
+
+        let s = sender.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let result = match system().await {
+                    Ok(connection) => run(connection, &sender).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("networkmanager: lost NetworkManager, reconnecting: {e}");
+                }
+                publish(&sender, Message::default());
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        s
+    });
+
+    SENDER.subscribe()
+}
+
+async fn system() -> zbus::Result<&'static Connection> {
+    static SYSTEM: OnceCell<Connection> = OnceCell::const_new();
+    SYSTEM.get_or_try_init(Connection::system).await
+}
+
+/// Runs `command` against NetworkManager. Its effect shows up through
+/// NetworkManager's signals.
+pub async fn command(command: Command) {
+    let result = async { apply(system().await?, &command).await }.await;
+    if let Err(e) = result {
+        eprintln!("networkmanager: {command:?} failed: {e}");
+    }
+}
+
+async fn apply(connection: &Connection, command: &Command) -> zbus::Result<()> {
+    let manager = NetworkManagerProxy::new(connection).await?;
+    match command {
+        Command::Activate(settings) => {
+            // "/" lets NetworkManager pick the device and access point.
+            let any = ObjectPath::from_static_str_unchecked("/");
+            manager
+                .activate_connection(&ObjectPath::try_from(settings.as_str())?, &any, &any)
+                .await?;
+        }
+        Command::Deactivate(active) => {
+            manager
+                .deactivate_connection(&ObjectPath::try_from(active.as_str())?)
+                .await?
+        }
+    }
+    Ok(())
+}
+
+/// Publishes the connections, then again whenever NetworkManager signals a
+/// change to them. Saved connections are only read again when they change,
+/// and active ones when the manager, one of them or its access point
+/// signals.
+async fn run(connection: &Connection, sender: &watch::Sender<Message>) -> zbus::Result<()> {
+    let changes = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .sender(SERVICE)?
+        .build();
+    let owners = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, SERVICE)?
+        .build();
+    let mut signals = stream::select(
+        MessageStream::for_match_rule(changes, connection, None).await?,
+        MessageStream::for_match_rule(owners, connection, None).await?,
+    );
+
+    let manager = NetworkManagerProxy::builder(connection)
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+        .await?;
+    let settings = SettingsProxy::new(connection).await?;
+    let mut saved = read_saved(connection, &settings).await?;
+    let mut active = read_active(connection, &manager).await?;
+    loop {
+        let msg = Message {
+            network: Some(Network {
+                active: active.clone(),
+                saved: saved.clone(),
+            }),
+        };
+        // Access points report their strength often, even when it hasn't
+        // changed enough to show.
+        if *sender.borrow() != msg {
+            publish(sender, msg);
+        }
+        let Some(signal) = signals.next().await else {
+            return Ok(());
+        };
+        let signal = signal?;
+        // Saved connections name the active ones.
+        let saved_changed = changes_saved(&signal);
+        if saved_changed {
+            saved = read_saved(connection, &settings).await?;
+        }
+        if saved_changed || changes_active(&signal, &active) {
+            active = read_active(connection, &manager).await?;
+        }
+    }
+}
+
+/// Whether a signal may have changed the active connections: it comes from
+/// the manager, which lists them, or from one of them or its access point.
+/// Other access points signal often while scanning, and don't matter.
+fn changes_active(signal: &zbus::Message, active: &[ActiveConnection]) -> bool {
+    let header = signal.header();
+    let Some(path) = header.path().map(|p| p.as_str()) else {
+        return false;
+    };
+    path == MANAGER_PATH
+        || active
+            .iter()
+            .any(|c| c.path == path || c.access_point.as_deref() == Some(path))
+}
+
+/// Whether a signal may have changed the saved connections: one was added,
+/// removed or updated, or NetworkManager restarted.
+fn changes_saved(signal: &zbus::Message) -> bool {
+    let header = signal.header();
+    let interface = header.interface().map(|i| i.as_str());
+    let member = header.member().map(|m| m.as_str());
+    matches!(
+        (interface, member),
+        (
+            Some(SETTINGS_INTERFACE),
+            Some("NewConnection" | "ConnectionRemoved")
+        ) | (Some(SETTINGS_CONNECTION_INTERFACE), Some("Updated"))
+            | (_, Some("NameOwnerChanged"))
+    )
+}
+
+async fn properties(
+    connection: &Connection,
+    path: &str,
+    interface: &'static str,
+) -> zbus::Result<HashMap<String, OwnedValue>> {
+    Ok(PropertiesProxy::builder(connection)
+        .destination(SERVICE)?
+        .path(path)?
+        .build()
+        .await?
+        .get_all(InterfaceName::from_static_str_unchecked(interface))
+        .await?)
+}
+
+async fn read_active(
+    connection: &Connection,
+    manager: &NetworkManagerProxy<'_>,
+) -> zbus::Result<Vec<ActiveConnection>> {
+    let primary = manager.primary_connection().await?;
+    let mut active = Vec::new();
+    for path in manager.active_connections().await? {
+        // Connections come and go; one vanishing mid-read isn't an error.
+        let Ok(active_properties) = properties(connection, path.as_str(), ACTIVE_INTERFACE).await
+        else {
+            continue;
+        };
+        let mut active_connection = parse_active(path.to_string(), &active_properties);
+        active_connection.primary = path == primary;
+        if active_connection.kind == ConnectionKind::Wifi
+            && let Some(access_point) = active_properties
+                .get("SpecificObject")
+                .and_then(|v| v.downcast_ref::<ObjectPath>().ok())
+                .filter(|p| p.as_str() != "/")
+        {
+            active_connection.strength =
+                properties(connection, access_point.as_str(), ACCESS_POINT_INTERFACE)
+                    .await
+                    .ok()
+                    .and_then(|ap| ap.get("Strength")?.downcast_ref::<u8>().ok());
+            active_connection.access_point = Some(access_point.to_string());
+        }
+        active.push(active_connection);
+    }
+    active.sort_by(|a, b| (!a.primary, &a.name).cmp(&(!b.primary, &b.name)));
+    Ok(active)
+}
+
+async fn read_saved(
+    connection: &Connection,
+    settings: &SettingsProxy<'_>,
+) -> zbus::Result<Vec<SavedConnection>> {
+    let mut saved = Vec::new();
+    for path in settings.list_connections().await? {
+        let Ok(proxy) = SettingsConnectionProxy::builder(connection)
+            .path(path.clone())?
+            .build()
+            .await
+        else {
+            continue;
+        };
+        if let Ok(settings) = proxy.get_settings().await
+            && let Some(saved_connection) = parse_saved(path.to_string(), &settings)
+        {
+            saved.push(saved_connection);
+        }
+    }
+    saved.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(saved)
+}
+
+fn parse_active(path: String, properties: &HashMap<String, OwnedValue>) -> ActiveConnection {
+    let string = |name: &str| {
+        properties
+            .get(name)
+            .and_then(|v| v.downcast_ref::<&str>().ok())
+            .unwrap_or_default()
+    };
+    let vpn = properties
+        .get("Vpn")
+        .and_then(|v| v.downcast_ref::<bool>().ok())
+        .unwrap_or(false);
+    // NM_ACTIVE_CONNECTION_STATE_ACTIVATED
+    let activated = properties
+        .get("State")
+        .and_then(|v| v.downcast_ref::<u32>().ok())
+        == Some(2);
+    ActiveConnection {
+        path,
+        settings: properties
+            .get("Connection")
+            .and_then(|v| v.downcast_ref::<ObjectPath>().ok())
+            .map(|p| p.to_string())
+            .unwrap_or_default(),
+        name: string("Id").to_string(),
+        kind: if vpn {
+            ConnectionKind::Vpn
+        } else {
+            ConnectionKind::from_nm(string("Type"))
+        },
+        activated,
+        primary: false,
+        strength: None,
+        access_point: None,
+    }
+}
+
+/// A saved connection, unless it's one there's no point switching to, like
+/// loopback.
+fn parse_saved(
+    path: String,
+    settings: &HashMap<String, HashMap<String, OwnedValue>>,
+) -> Option<SavedConnection> {
+    let connection = settings.get("connection")?;
+    let string = |name: &str| connection.get(name)?.downcast_ref::<&str>().ok();
+    let kind = ConnectionKind::from_nm(string("type")?);
+    (kind != ConnectionKind::Other).then(|| SavedConnection {
+        path,
+        name: string("id").unwrap_or_default().to_string(),
+        kind,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use zbus::{interface, object_server::SignalEmitter, zvariant::Value};
+
+    use super::*;
+    use crate::util::test_bus::TestBus;
+
+    const ROOT: &str = "/org/freedesktop/NetworkManager";
+    const SETTINGS: &str = "/org/freedesktop/NetworkManager/Settings";
+    const HOME_SETTINGS: &str = "/org/freedesktop/NetworkManager/Settings/1";
+    const VPN_SETTINGS: &str = "/org/freedesktop/NetworkManager/Settings/2";
+    const LOOPBACK_SETTINGS: &str = "/org/freedesktop/NetworkManager/Settings/3";
+    const HOME_ACTIVE: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+    const VPN_ACTIVE: &str = "/org/freedesktop/NetworkManager/ActiveConnection/2";
+    const ACCESS_POINT: &str = "/org/freedesktop/NetworkManager/AccessPoint/1";
+    const OTHER_ACCESS_POINT: &str = "/org/freedesktop/NetworkManager/AccessPoint/2";
+
+    fn path(path: &'static str) -> OwnedObjectPath {
+        ObjectPath::from_static_str_unchecked(path).into()
+    }
+
+    struct FakeManager {
+        active: Vec<OwnedObjectPath>,
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager")]
+    impl FakeManager {
+        async fn activate_connection(
+            &mut self,
+            connection: OwnedObjectPath,
+            _device: OwnedObjectPath,
+            _specific_object: OwnedObjectPath,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> OwnedObjectPath {
+            assert_eq!(connection.as_str(), VPN_SETTINGS);
+            self.active.push(path(VPN_ACTIVE));
+            self.active_connections_changed(&emitter).await.unwrap();
+            path(VPN_ACTIVE)
+        }
+
+        async fn deactivate_connection(
+            &mut self,
+            active_connection: OwnedObjectPath,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) {
+            self.active.retain(|p| *p != active_connection);
+            self.active_connections_changed(&emitter).await.unwrap();
+        }
+
+        #[zbus(property)]
+        fn active_connections(&self) -> Vec<OwnedObjectPath> {
+            self.active.clone()
+        }
+
+        #[zbus(property)]
+        fn primary_connection(&self) -> OwnedObjectPath {
+            path(HOME_ACTIVE)
+        }
+    }
+
+    struct FakeActive {
+        id: &'static str,
+        kind: &'static str,
+        vpn: bool,
+        settings: &'static str,
+        specific_object: &'static str,
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+    impl FakeActive {
+        #[zbus(property)]
+        fn id(&self) -> &str {
+            self.id
+        }
+
+        #[zbus(property, name = "Type")]
+        fn kind(&self) -> &str {
+            self.kind
+        }
+
+        #[zbus(property)]
+        fn vpn(&self) -> bool {
+            self.vpn
+        }
+
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            2
+        }
+
+        #[zbus(property)]
+        fn connection(&self) -> OwnedObjectPath {
+            path(self.settings)
+        }
+
+        #[zbus(property)]
+        fn specific_object(&self) -> OwnedObjectPath {
+            path(self.specific_object)
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeAccessPoint {
+        /// How many times the strength has been read.
+        reads: AtomicUsize,
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+    impl FakeAccessPoint {
+        #[zbus(property)]
+        fn strength(&self) -> u8 {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            73
+        }
+    }
+
+    struct FakeSettings {
+        connections: Vec<OwnedObjectPath>,
+        /// How many times the connections have been listed.
+        lists: AtomicUsize,
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager.Settings")]
+    impl FakeSettings {
+        fn list_connections(&self) -> Vec<OwnedObjectPath> {
+            self.lists.fetch_add(1, Ordering::Relaxed);
+            self.connections.clone()
+        }
+
+        #[zbus(signal)]
+        async fn connection_removed(
+            emitter: &SignalEmitter<'_>,
+            connection: ObjectPath<'_>,
+        ) -> zbus::Result<()>;
+    }
+
+    struct FakeSettingsConnection {
+        id: &'static str,
+        kind: &'static str,
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+    impl FakeSettingsConnection {
+        fn get_settings(&self) -> HashMap<String, HashMap<String, OwnedValue>> {
+            let connection = HashMap::from([
+                ("id".to_string(), Value::from(self.id).try_into().unwrap()),
+                (
+                    "type".to_string(),
+                    Value::from(self.kind).try_into().unwrap(),
+                ),
+            ]);
+            HashMap::from([("connection".to_string(), connection)])
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_fake_networkmanager() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+
+        let service = bus
+            .builder()
+            .name(SERVICE)
+            .unwrap()
+            .serve_at(
+                ROOT,
+                FakeManager {
+                    active: vec![path(HOME_ACTIVE)],
+                },
+            )
+            .unwrap()
+            .serve_at(
+                HOME_ACTIVE,
+                FakeActive {
+                    id: "Home",
+                    kind: "802-11-wireless",
+                    vpn: false,
+                    settings: HOME_SETTINGS,
+                    specific_object: ACCESS_POINT,
+                },
+            )
+            .unwrap()
+            .serve_at(
+                VPN_ACTIVE,
+                FakeActive {
+                    id: "Work",
+                    kind: "vpn",
+                    vpn: true,
+                    settings: VPN_SETTINGS,
+                    specific_object: "/",
+                },
+            )
+            .unwrap()
+            .serve_at(ACCESS_POINT, FakeAccessPoint::default())
+            .unwrap()
+            .serve_at(OTHER_ACCESS_POINT, FakeAccessPoint::default())
+            .unwrap()
+            .serve_at(
+                SETTINGS,
+                FakeSettings {
+                    connections: vec![
+                        path(HOME_SETTINGS),
+                        path(VPN_SETTINGS),
+                        path(LOOPBACK_SETTINGS),
+                    ],
+                    lists: AtomicUsize::new(0),
+                },
+            )
+            .unwrap()
+            .serve_at(
+                HOME_SETTINGS,
+                FakeSettingsConnection {
+                    id: "Home",
+                    kind: "802-11-wireless",
+                },
+            )
+            .unwrap()
+            .serve_at(
+                VPN_SETTINGS,
+                FakeSettingsConnection {
+                    id: "Work",
+                    kind: "vpn",
+                },
+            )
+            .unwrap()
+            .serve_at(
+                LOOPBACK_SETTINGS,
+                FakeSettingsConnection {
+                    id: "lo",
+                    kind: "loopback",
+                },
+            )
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let client = bus.connect().await;
+        let (sender, mut receiver) = watch::channel(Message::default());
+        let c = client.clone();
+        tokio::spawn(async move { run(&c, &sender).await });
+
+        let mut next = async || {
+            tokio::time::timeout(Duration::from_secs(5), receiver.changed())
+                .await
+                .expect("network change was not published")
+                .unwrap();
+            receiver.borrow_and_update().network.clone().unwrap()
+        };
+
+        let network = next().await;
+        let primary = network.primary().unwrap();
+        assert_eq!(primary.name, "Home");
+        assert_eq!(primary.kind, ConnectionKind::Wifi);
+        assert_eq!(primary.strength, Some(73));
+        assert!(primary.activated);
+        assert!(network.vpn().is_none());
+        let saved: Vec<_> = network.saved.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(saved, ["Home", "Work"]);
+        assert!(network.activation(&network.saved[0]).is_some());
+        assert!(network.activation(&network.saved[1]).is_none());
+
+        apply(&client, &Command::Activate(VPN_SETTINGS.into()))
+            .await
+            .unwrap();
+        let network = next().await;
+        assert_eq!(network.vpn().unwrap().name, "Work");
+        assert_eq!(network.primary().unwrap().name, "Home");
+
+        apply(&client, &Command::Deactivate(VPN_ACTIVE.into()))
+            .await
+            .unwrap();
+        assert!(next().await.vpn().is_none());
+
+        // Access points nothing is connected through are ignored.
+        let object_server = service.object_server();
+        let followed = object_server
+            .interface::<_, FakeAccessPoint>(ACCESS_POINT)
+            .await
+            .unwrap();
+        let other = object_server
+            .interface::<_, FakeAccessPoint>(OTHER_ACCESS_POINT)
+            .await
+            .unwrap();
+        let reads = followed.get().await.reads.load(Ordering::Relaxed);
+        other
+            .get()
+            .await
+            .strength_changed(other.signal_emitter())
+            .await
+            .unwrap();
+
+        // Only changes to the saved connections have them listed again.
+        let settings = service
+            .object_server()
+            .interface::<_, FakeSettings>(SETTINGS)
+            .await
+            .unwrap();
+        assert_eq!(settings.get().await.lists.load(Ordering::Relaxed), 1);
+        settings
+            .get_mut()
+            .await
+            .connections
+            .retain(|p| p.as_str() != VPN_SETTINGS);
+        FakeSettings::connection_removed(
+            settings.signal_emitter(),
+            ObjectPath::from_static_str_unchecked(VPN_SETTINGS),
+        )
+        .await
+        .unwrap();
+        let saved: Vec<_> = next().await.saved.into_iter().map(|s| s.name).collect();
+        assert_eq!(saved, ["Home"]);
+        assert_eq!(settings.get().await.lists.load(Ordering::Relaxed), 2);
+        // Read again for the saved connections, but not for the other
+        // access point.
+        assert_eq!(
+            followed.get().await.reads.load(Ordering::Relaxed),
+            reads + 1
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::{collections::HashMap, sync::LazyLock, time::Duration};
 
 use niri_ipc::{
-    Request, Window, Workspace,
+    Action, Event, KeyboardLayouts, Request, Response, Window, Workspace,
     socket::Socket,
     state::{EventStreamState, EventStreamStatePart},
 };
@@ -12,6 +12,12 @@ const RECONNECT_DELAY: Duration = Duration::from_secs(1);
 #[derive(Debug)]
 pub struct Message {
     pub outputs: HashMap<String, Output>,
+    /// `None` until niri has reported them.
+    pub keyboard_layouts: Option<KeyboardLayouts>,
+    pub overview_open: bool,
+    /// Whether niri failed to load its config the last time it tried. niri
+    /// keeps running with the previous one.
+    pub config_failed: bool,
 }
 
 #[derive(Debug, Default)]
@@ -19,6 +25,11 @@ pub struct Output {
     pub workspaces: Vec<Workspace>,
     pub window: String,
     pub workspace_windows: Vec<Window>,
+    /// The windows on each of the output's workspaces, by workspace ID,
+    /// sorted like `workspace_windows`.
+    pub windows_by_workspace: HashMap<u64, Vec<Window>>,
+    /// Logical size, as (width, height), once niri has told us.
+    pub size: Option<(u32, u32)>,
 }
 
 fn produce(state: &EventStreamState) -> Message {
@@ -43,23 +54,45 @@ fn produce(state: &EventStreamState) -> Message {
         output.workspaces.push(ws.clone());
     }
 
+    for window in state.windows.windows.values() {
+        let Some(id) = window.workspace_id else {
+            continue;
+        };
+        let Some(output) = state
+            .workspaces
+            .workspaces
+            .get(&id)
+            .and_then(|ws| outputs.get_mut(ws.output.as_ref()?))
+        else {
+            continue;
+        };
+        output
+            .windows_by_workspace
+            .entry(id)
+            .or_default()
+            .push(window.clone());
+    }
+
     for (_, output) in outputs.iter_mut() {
         output.workspaces.sort_by_key(|ws| ws.idx);
 
+        for windows in output.windows_by_workspace.values_mut() {
+            windows.sort_by_key(|w| w.layout.pos_in_scrolling_layout);
+        }
         let active_workspace_id = output.workspaces.iter().find(|ws| ws.is_active).unwrap().id;
-        output.workspace_windows = state
-            .windows
-            .windows
-            .values()
-            .filter(|w| w.workspace_id == Some(active_workspace_id))
+        output.workspace_windows = output
+            .windows_by_workspace
+            .get(&active_workspace_id)
             .cloned()
-            .collect();
-        output
-            .workspace_windows
-            .sort_by_key(|w| w.layout.pos_in_scrolling_layout);
+            .unwrap_or_default();
     }
 
-    Message { outputs }
+    Message {
+        outputs,
+        keyboard_layouts: state.keyboard_layouts.keyboard_layouts.clone(),
+        overview_open: state.overview.is_open,
+        config_failed: state.config.failed,
+    }
 }
 
 pub fn listen() -> watch::Receiver<Message> {
@@ -83,6 +116,23 @@ pub fn listen() -> watch::Receiver<Message> {
     SENDER.subscribe()
 }
 
+/// Asks niri to perform `action`, on a connection of its own so it doesn't
+/// wait behind the event stream.
+pub async fn action(action: Action) {
+    let result = tokio::task::spawn_blocking(move || -> eyre::Result<()> {
+        Socket::connect()?
+            .send(Request::Action(action))?
+            .map_err(|e| eyre::eyre!("niri rejected action: {e}"))?;
+        Ok(())
+    })
+    .await;
+    match result {
+        Ok(Err(e)) => eprintln!("niri: {e}"),
+        Err(e) => eprintln!("niri: action panicked: {e}"),
+        Ok(Ok(())) => {}
+    }
+}
+
 /// Renders a workspace's bar label exactly like the workspace consumer does:
 /// its name, or its index when unnamed. Kept in sync with `consumer::workspace`.
 #[cfg(test)]
@@ -102,6 +152,7 @@ fn run_stream(sender: &watch::Sender<Message>) -> eyre::Result<()> {
         .send(Request::EventStream)?
         .map_err(|e| eyre::eyre!("niri rejected EventStream request: {e}"))?;
     let mut read_event = socket.read_events();
+    let mut sizes = HashMap::new();
 
     loop {
         let event = match read_event() {
@@ -110,8 +161,17 @@ fn run_stream(sender: &watch::Sender<Message>) -> eyre::Result<()> {
             Err(e) if e.kind() == std::io::ErrorKind::InvalidData => continue,
             Err(e) => return Err(e.into()),
         };
+        if may_resize_outputs(&event) {
+            sizes = output_sizes().unwrap_or_else(|e| {
+                eprintln!("niri: couldn't get output sizes: {e}");
+                HashMap::new()
+            });
+        }
         state.apply(event);
-        let msg = produce(&state);
+        let mut msg = produce(&state);
+        for (name, output) in msg.outputs.iter_mut() {
+            output.size = sizes.get(name).copied();
+        }
         // All receivers gone means the app is shutting down; stop the stream.
         if sender.send(msg).is_err() {
             return Ok(());
@@ -119,6 +179,34 @@ fn run_stream(sender: &watch::Sender<Message>) -> eyre::Result<()> {
     }
 }
 
+/// Whether an output may have been added, removed or resized. The event
+/// stream doesn't cover outputs, but workspaces move when they come and go,
+/// and a config reload may change their mode or scale. Window layout changes
+/// would catch the rest, but they stream in during every resize and move.
+fn may_resize_outputs(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::WorkspacesChanged { .. } | Event::ConfigLoaded { .. }
+    )
+}
+
+/// The logical size of each output, by name.
+fn output_sizes() -> eyre::Result<HashMap<String, (u32, u32)>> {
+    let reply = Socket::connect()?
+        .send(Request::Outputs)?
+        .map_err(|e| eyre::eyre!("niri rejected Outputs request: {e}"))?;
+    let Response::Outputs(outputs) = reply else {
+        eyre::bail!("unexpected reply to Outputs request: {reply:?}");
+    };
+    Ok(outputs
+        .into_iter()
+        .filter_map(|(name, output)| {
+            let logical = output.logical?;
+            Some((name, (logical.width, logical.height)))
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,6 +239,18 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn refetches_output_sizes_on_config_reload() {
+        assert!(may_resize_outputs(&Event::ConfigLoaded { failed: false }));
+        assert!(may_resize_outputs(&Event::WorkspacesChanged {
+            workspaces: vec![]
+        }));
+        assert!(!may_resize_outputs(&Event::WindowLayoutsChanged {
+            changes: vec![]
+        }));
+        assert!(!may_resize_outputs(&Event::WindowFocusChanged { id: None }));
+    }
+
     /// The reported scenario: one `main`, unique indices, single output.
     /// `produce` must yield each workspace exactly once, in index order.
     #[test]
@@ -214,4 +314,79 @@ mod tests {
             .collect();
         assert_eq!(idxs, [1, 2, 3]);
     }
+
+    fn window(id: u64, workspace_id: u64, column: usize) -> Window {
+        Window {
+            id,
+            title: None,
+            app_id: None,
+            pid: None,
+            workspace_id: Some(workspace_id),
+            is_focused: false,
+            is_floating: false,
+            is_urgent: false,
+            layout: niri_ipc::WindowLayout {
+                pos_in_scrolling_layout: Some((column, 1)),
+                tile_size: (100.0, 100.0),
+                window_size: (100, 100),
+                tile_pos_in_workspace_view: None,
+                window_offset_in_tile: (0.0, 0.0),
+            },
+            focus_timestamp: None,
+        }
+    }
+
+    #[test]
+    fn windows_are_listed_per_workspace() {
+        let mut st = state(vec![
+            ws(1, 1, None, "DP-1", true),
+            ws(2, 2, None, "DP-1", false),
+            ws(3, 1, None, "HDMI-1", true),
+        ]);
+        st.windows.windows = [
+            window(10, 1, 2),
+            window(11, 1, 1),
+            window(12, 2, 1),
+            window(13, 3, 1),
+        ]
+        .into_iter()
+        .map(|w| (w.id, w))
+        .collect();
+
+        let msg = produce(&st);
+        let ids = |windows: &[Window]| windows.iter().map(|w| w.id).collect::<Vec<_>>();
+        let dp1 = &msg.outputs["DP-1"];
+        assert_eq!(ids(&dp1.workspace_windows), [11, 10]);
+        assert_eq!(ids(&dp1.windows_by_workspace[&1]), [11, 10]);
+        assert_eq!(ids(&dp1.windows_by_workspace[&2]), [12]);
+        assert!(!dp1.windows_by_workspace.contains_key(&3));
+        assert_eq!(ids(&msg.outputs["HDMI-1"].windows_by_workspace[&3]), [13]);
+    }
+
+    #[test]
+    fn keyboard_layouts_are_kept() {
+        let mut st = state(vec![ws(1, 1, None, "DP-1", true)]);
+        assert_eq!(produce(&st).keyboard_layouts, None);
+
+        let layouts = KeyboardLayouts {
+            names: vec!["English (US)".into(), "German".into()],
+            current_idx: 1,
+        };
+        st.keyboard_layouts.keyboard_layouts = Some(layouts.clone());
+        assert_eq!(produce(&st).keyboard_layouts, Some(layouts));
+    }
+
+    #[test]
+    fn overview_and_config_failure_are_kept() {
+        let mut st = state(vec![ws(1, 1, None, "DP-1", true)]);
+        let msg = produce(&st);
+        assert!(!msg.overview_open);
+        assert!(!msg.config_failed);
+
+        st.overview.is_open = true;
+        st.config.failed = true;
+        let msg = produce(&st);
+        assert!(msg.overview_open);
+        assert!(msg.config_failed);
+    }
 }
@@ -0,0 +1,475 @@
+//! A notification daemon, serving `org.freedesktop.Notifications` on the
+//! session bus. Notifications stay in the history until they're dismissed,
+//! their app closes them, or enough newer ones push them out; how
+//! long they're shown as toasts is up to the consumer.
+
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+use jiff::Timestamp;
+use tokio::sync::{OnceCell, watch};
+use zbus::{
+    Connection,
+    fdo::{RequestNameFlags, RequestNameReply},
+    object_server::SignalEmitter,
+    zvariant::OwnedValue,
+};
+
+use crate::{producer::publish, util::notify::Urgency};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const NAME: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+
+/// The most notifications kept. Beyond it, the oldest expire.
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Debug, Default)]
+pub struct Message {
+    /// Oldest first.
+    pub notifications: Vec<Notification>,
+    /// While set, only critical notifications should be shown as toasts.
+    pub do_not_disturb: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    pub urgency: Urgency,
+    /// Pairs of action key and label. The "default" action is the one to
+    /// invoke when the notification itself is clicked.
+    pub actions: Vec<(String, String)>,
+    pub timeout: Timeout,
+    /// When it arrived, or was last replaced.
+    pub received: Timestamp,
+}
+
+impl Notification {
+    pub fn has_default_action(&self) -> bool {
+        self.actions.iter().any(|(key, _)| key == "default")
+    }
+}
+
+/// How long the app asked for a notification to be shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    /// Up to us.
+    #[default]
+    Default,
+    Never,
+    After(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Removes the notification, telling its app it was dismissed.
+    Dismiss(u32),
+    DismissAll,
+    /// Invokes an action by key, then dismisses the notification.
+    Invoke(u32, String),
+    ToggleDoNotDisturb,
+}
+
+/// Why a notification was closed, per the spec.
+#[derive(Debug, Clone, Copy)]
+enum CloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    Closed = 3,
+}
+
+static SENDER: OnceLock<watch::Sender<Message>> = OnceLock::new();
+
+/// Starts the daemon, unless it's running already.
+pub fn listen() -> watch::Receiver<Message> {
+    let sender = SENDER.get_or_init(|| {
+        let (sender, _) = watch::channel(Message::default());
+
+        let s = sender.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let result = match session().await {
+                    Ok(connection) => run(connection, &sender).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("notifications: connection ended, reconnecting: {e}");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        s
+    });
+
+    sender.subscribe()
+}
+
+/// Follows the daemon if something has started it, without starting it.
+pub fn running() -> Option<watch::Receiver<Message>> {
+    SENDER.get().map(watch::Sender::subscribe)
+}
+
+async fn session() -> zbus::Result<&'static Connection> {
+    static SESSION: OnceCell<Connection> = OnceCell::const_new();
+    SESSION.get_or_try_init(Connection::session).await
+}
+
+/// Runs `command` against the daemon.
+pub async fn command(command: Command) {
+    let result = async { apply(session().await?, command.clone()).await }.await;
+    if let Err(e) = result {
+        eprintln!("notifications: {command:?} failed: {e}");
+    }
+}
+
+/// Serves the daemon. If another one already has the name, we queue for it,
+/// and take over once that one exits.
+async fn run(connection: &Connection, sender: &watch::Sender<Message>) -> zbus::Result<()> {
+    connection
+        .object_server()
+        .at(PATH, Server::new(sender.clone()))
+        .await?;
+    let reply = connection
+        .request_name_with_flags(NAME, RequestNameFlags::AllowReplacement.into())
+        .await?;
+    if reply == RequestNameReply::InQueue {
+        eprintln!("notifications: another daemon is running, waiting for it to exit");
+    }
+    std::future::pending().await
+}
+
+async fn apply(connection: &Connection, command: Command) -> zbus::Result<()> {
+    let server = connection
+        .object_server()
+        .interface::<_, Server>(PATH)
+        .await?;
+    let emitter = server.signal_emitter();
+    let mut server = server.get_mut().await;
+    match command {
+        Command::Dismiss(id) => server.close(emitter, id, CloseReason::Dismissed).await?,
+        Command::DismissAll => {
+            let ids: Vec<_> = server.notifications.iter().map(|n| n.id).collect();
+            for id in ids {
+                server.close(emitter, id, CloseReason::Dismissed).await?;
+            }
+        }
+        Command::Invoke(id, key) => {
+            Server::action_invoked(emitter, id, &key).await?;
+            server.close(emitter, id, CloseReason::Dismissed).await?;
+        }
+        Command::ToggleDoNotDisturb => {
+            server.do_not_disturb = !server.do_not_disturb;
+            server.publish();
+        }
+    }
+    Ok(())
+}
+
+struct Server {
+    notifications: Vec<Notification>,
+    do_not_disturb: bool,
+    last_id: u32,
+    sender: watch::Sender<Message>,
+}
+
+impl Server {
+    fn new(sender: watch::Sender<Message>) -> Self {
+        Server {
+            notifications: Vec::new(),
+            do_not_disturb: false,
+            last_id: 0,
+            sender,
+        }
+    }
+
+    fn publish(&self) {
+        publish(
+            &self.sender,
+            Message {
+                notifications: self.notifications.clone(),
+                do_not_disturb: self.do_not_disturb,
+            },
+        );
+    }
+
+    async fn close(
+        &mut self,
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        reason: CloseReason,
+    ) -> zbus::Result<()> {
+        let before = self.notifications.len();
+        self.notifications.retain(|n| n.id != id);
+        if self.notifications.len() != before {
+            Self::notification_closed(emitter, id, reason as u32).await?;
+            self.publish();
+        }
+        Ok(())
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.Notifications")]
+impl Server {
+    fn get_capabilities(&self) -> Vec<&str> {
+        vec!["actions", "body", "persistence"]
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &mut self,
+        app_name: String,
+        replaces_id: u32,
+        _app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> zbus::fdo::Result<u32> {
+        let urgency = match hints
+            .get("urgency")
+            .and_then(|v| v.downcast_ref::<u8>().ok())
+        {
+            Some(0) => Urgency::Low,
+            Some(2) => Urgency::Critical,
+            _ => Urgency::Normal,
+        };
+        let timeout = match expire_timeout {
+            0 => Timeout::Never,
+            ms if ms > 0 => Timeout::After(Duration::from_millis(ms as u64)),
+            _ => Timeout::Default,
+        };
+        let replaces = self.notifications.iter().position(|n| n.id == replaces_id);
+        let id = match replaces {
+            Some(_) => replaces_id,
+            None => {
+                self.last_id = self.last_id.wrapping_add(1).max(1);
+                self.last_id
+            }
+        };
+        let notification = Notification {
+            id,
+            app_name,
+            summary,
+            body,
+            urgency,
+            actions: actions
+                .chunks_exact(2)
+                .map(|pair| (pair[0].clone(), pair[1].clone()))
+                .collect(),
+            timeout,
+            received: Timestamp::now(),
+        };
+        // A replacement is news again, so it goes to the end.
+        if let Some(i) = replaces {
+            self.notifications.remove(i);
+        }
+        while self.notifications.len() >= HISTORY_LIMIT {
+            let oldest = self.notifications[0].id;
+            self.close(&emitter, oldest, CloseReason::Expired).await?;
+        }
+        self.notifications.push(notification);
+        self.publish();
+        Ok(id)
+    }
+
+    async fn close_notification(
+        &mut self,
+        id: u32,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> zbus::fdo::Result<()> {
+        self.close(&emitter, id, CloseReason::Closed).await?;
+        Ok(())
+    }
+
+    fn get_server_information(&self) -> (&str, &str, &str, &str) {
+        ("rustybar", "rustybar", env!("CARGO_PKG_VERSION"), "1.2")
+    }
+
+    #[zbus(signal)]
+    async fn notification_closed(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use zbus::zvariant::Value;
+
+    use super::*;
+    use crate::util::test_bus::TestBus;
+
+    #[zbus::proxy(
+        interface = "org.freedesktop.Notifications",
+        default_service = "org.freedesktop.Notifications",
+        default_path = "/org/freedesktop/Notifications"
+    )]
+    trait Notifications {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: &str,
+            replaces_id: u32,
+            app_icon: &str,
+            summary: &str,
+            body: &str,
+            actions: &[&str],
+            hints: HashMap<&str, Value<'_>>,
+            expire_timeout: i32,
+        ) -> zbus::Result<u32>;
+
+        fn close_notification(&self, id: u32) -> zbus::Result<()>;
+
+        #[zbus(signal)]
+        fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+
+        #[zbus(signal)]
+        fn action_invoked(&self, id: u32, action_key: &str) -> zbus::Result<()>;
+    }
+
+    #[tokio::test]
+    async fn serves_notifications() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+
+        let daemon = bus.connect().await;
+        let (sender, mut receiver) = watch::channel(Message::default());
+        let d = daemon.clone();
+        tokio::spawn(async move { run(&d, &sender).await });
+
+        let client = bus.connect().await;
+        let proxy = NotificationsProxy::new(&client).await.unwrap();
+        let mut closed = proxy.receive_notification_closed().await.unwrap();
+        let mut invoked = proxy.receive_action_invoked().await.unwrap();
+        // The daemon may not have its name yet.
+        let notify = async |replaces_id, summary, actions: &[&str], urgency: u8, timeout| {
+            let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+            tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    let result = proxy
+                        .notify(
+                            "app",
+                            replaces_id,
+                            "",
+                            summary,
+                            "body",
+                            actions,
+                            hints.clone(),
+                            timeout,
+                        )
+                        .await;
+                    if let Ok(id) = result {
+                        return id;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap()
+        };
+        let mut latest = async || {
+            receiver.changed().await.unwrap();
+            let msg = receiver.borrow_and_update();
+            (msg.notifications.clone(), msg.do_not_disturb)
+        };
+
+        let first = notify(0, "First", &["default", "Open"], 2, -1).await;
+        let (notifications, _) = latest().await;
+        assert_eq!(notifications[0].summary, "First");
+        assert_eq!(notifications[0].urgency, Urgency::Critical);
+        assert_eq!(notifications[0].timeout, Timeout::Default);
+        assert!(notifications[0].has_default_action());
+
+        let second = notify(0, "Second", &[], 1, 3000).await;
+        assert_ne!(first, second);
+        let (notifications, _) = latest().await;
+        assert_eq!(
+            notifications[1].timeout,
+            Timeout::After(Duration::from_secs(3))
+        );
+
+        // Replacing keeps the id, but moves it to the end.
+        assert_eq!(notify(first, "First again", &[], 1, 0).await, first);
+        let (notifications, _) = latest().await;
+        let summaries: Vec<_> = notifications.iter().map(|n| n.summary.as_str()).collect();
+        assert_eq!(summaries, ["Second", "First again"]);
+        assert_eq!(notifications[1].timeout, Timeout::Never);
+
+        proxy.close_notification(second).await.unwrap();
+        let args = closed.next().await.unwrap();
+        assert_eq!(*args.args().unwrap().id(), second);
+        assert_eq!(*args.args().unwrap().reason(), 3);
+        assert_eq!(latest().await.0.len(), 1);
+
+        apply(&daemon, Command::ToggleDoNotDisturb).await.unwrap();
+        assert!(latest().await.1);
+
+        apply(&daemon, Command::Invoke(first, "default".into()))
+            .await
+            .unwrap();
+        let args = invoked.next().await.unwrap();
+        assert_eq!(*args.args().unwrap().id(), first);
+        assert_eq!(*args.args().unwrap().action_key(), "default");
+        let args = closed.next().await.unwrap();
+        assert_eq!(*args.args().unwrap().reason(), 2);
+        assert!(latest().await.0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn expires_the_oldest_beyond_the_limit() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+
+        let daemon = bus.connect().await;
+        let (sender, receiver) = watch::channel(Message::default());
+        tokio::spawn(async move { run(&daemon, &sender).await });
+
+        let client = bus.connect().await;
+        let proxy = NotificationsProxy::new(&client).await.unwrap();
+        let mut closed = proxy.receive_notification_closed().await.unwrap();
+        let notify = async |summary: &str| {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    let result = proxy
+                        .notify("app", 0, "", summary, "", &[], HashMap::new(), -1)
+                        .await;
+                    if let Ok(id) = result {
+                        return id;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap()
+        };
+
+        let first = notify("0").await;
+        for i in 1..=HISTORY_LIMIT {
+            notify(&i.to_string()).await;
+        }
+
+        let args = closed.next().await.unwrap();
+        assert_eq!(*args.args().unwrap().id(), first);
+        assert_eq!(*args.args().unwrap().reason(), 1);
+        let msg = receiver.borrow();
+        assert_eq!(msg.notifications.len(), HISTORY_LIMIT);
+        assert_eq!(msg.notifications[0].summary, "1");
+    }
+}
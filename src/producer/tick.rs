@@ -1,23 +1,140 @@
 use std::{
-    sync::LazyLock,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock,
+        atomic::{AtomicBool, Ordering},
+    },
     time::{Duration, Instant},
 };
 
 use jiff::Zoned;
-use starship_battery::State;
-use sysinfo::{Components, CpuRefreshKind, MemoryRefreshKind, Networks, RefreshKind, System};
+use starship_battery::{
+    State,
+    units::{energy, power, ratio, time},
+};
+use sysinfo::{
+    Components, CpuRefreshKind, DiskRefreshKind, Disks, InterfaceOperationalState,
+    MemoryRefreshKind, Networks, RefreshKind, System,
+};
 use tokio::{sync::watch, time::sleep};
 
+use crate::producer::diskstats::{self, DiskIo};
+
 #[derive(Debug, Default, Clone)]
 pub struct Battery {
+    /// Vendor and model, when reported.
+    pub name: Option<String>,
     pub charge: f32,
     pub state: State,
+    /// Stored energy, in watt-hours.
+    pub energy: f32,
+    /// Energy when fully charged, in watt-hours.
+    pub energy_full: f32,
+    /// Energy when fully charged as designed, in watt-hours.
+    pub energy_full_design: f32,
+    /// Rate of charge or discharge, in watts.
+    pub power: f32,
+    pub time_to_full: Option<Duration>,
+    pub time_to_empty: Option<Duration>,
+}
+
+impl Battery {
+    /// Full capacity as a fraction of design capacity.
+    pub fn health(&self) -> f32 {
+        if self.energy_full_design > 0.0 {
+            self.energy_full / self.energy_full_design
+        } else {
+            1.0
+        }
+    }
+
+    /// Treats `batteries` as one large battery. `None` if there are none.
+    pub fn combine(batteries: &[Battery]) -> Option<Battery> {
+        let [first, rest @ ..] = batteries else {
+            return None;
+        };
+        if rest.is_empty() {
+            return Some(first.clone());
+        }
+
+        let energy: f32 = batteries.iter().map(|b| b.energy).sum();
+        let energy_full: f32 = batteries.iter().map(|b| b.energy_full).sum();
+        let energy_full_design = batteries.iter().map(|b| b.energy_full_design).sum();
+        let any = |state| batteries.iter().any(|b| b.state == state);
+        let all = |state| batteries.iter().all(|b| b.state == state);
+        let state = if any(State::Charging) {
+            State::Charging
+        } else if any(State::Discharging) {
+            State::Discharging
+        } else if all(State::Full) {
+            State::Full
+        } else if all(State::Empty) {
+            State::Empty
+        } else {
+            State::Unknown
+        };
+
+        // Batteries may charge one another, so rates of batteries in the
+        // wrong direction count against the total.
+        let power: f32 = batteries
+            .iter()
+            .map(|b| match (state, b.state) {
+                (State::Charging, State::Discharging) | (State::Discharging, State::Charging) => {
+                    -b.power
+                }
+                _ => b.power,
+            })
+            .sum();
+        // Miscalibrated batteries can hold more than their full energy.
+        let hours = |wh: f32| {
+            (power > 0.0)
+                .then(|| Duration::try_from_secs_f32(wh.max(0.0) / power * 3600.0).ok())
+                .flatten()
+        };
+
+        Some(Battery {
+            name: None,
+            charge: if energy_full > 0.0 {
+                energy / energy_full
+            } else {
+                0.0
+            },
+            state,
+            energy,
+            energy_full,
+            energy_full_design,
+            power: power.max(0.0),
+            time_to_full: (state == State::Charging)
+                .then(|| hours(energy_full - energy))
+                .flatten(),
+            time_to_empty: (state == State::Discharging)
+                .then(|| hours(energy))
+                .flatten(),
+        })
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Network {
-    pub bytes_received: u64,
-    pub bytes_transmitted: u64,
+    /// Every interface, sorted by name.
+    pub interfaces: Vec<Interface>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Interface {
+    pub name: String,
+    /// Bytes per second received since the last tick.
+    pub received: u64,
+    /// Bytes per second transmitted since the last tick.
+    pub transmitted: u64,
+    /// Whether the link can pass packets. Interfaces that don't report a
+    /// state, such as most tunnels, count as up.
+    pub up: bool,
+    /// Whether the interface is backed by a device, as opposed to being
+    /// virtual, like loopback, bridges or tunnels.
+    pub physical: bool,
+    pub addresses: Vec<IpAddr>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -25,16 +142,84 @@ pub struct Cpu {
     pub min: f32,
     pub avg: f32,
     pub max: f32,
+    /// Usage of each logical core, in kernel order.
+    pub cores: Vec<f32>,
+    /// Usage of each physical core, averaged over its hardware threads.
+    pub physical_cores: Vec<f32>,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Temperature {
-    pub max: f32,
+    /// Every sensor currently reporting a temperature.
+    pub sensors: Vec<Sensor>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Sensor {
+    pub label: String,
+    /// In degrees Celsius.
+    pub temp: f32,
+    /// The temperature the hardware considers critical, if it reports one.
+    pub critical: Option<f32>,
+}
+
+impl Sensor {
+    pub fn is_critical(&self) -> bool {
+        self.critical.is_some_and(|c| self.temp >= c)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Memory {
+    pub total: u64,
     pub available: u64,
+    pub used: u64,
+    pub swap_total: u64,
+    pub swap_used: u64,
+    /// Summed over all zram devices, if there are any. `None` unless
+    /// [`read_zram`] was called.
+    pub zram: Option<Zram>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SystemInfo {
+    /// Load averages over 1, 5 and 15 minutes.
+    pub load: [f64; 3],
+    /// Logical CPUs, for putting the load in proportion.
+    pub cpus: usize,
+    pub uptime: Duration,
+    /// 0 unless [`count_processes`] was called.
+    pub processes: usize,
+    /// Threads currently runnable, as reported by `/proc/loadavg`.
+    pub running_threads: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Disk {
+    pub mount_point: PathBuf,
+    /// The device, e.g. "/dev/nvme0n1p2".
+    pub name: String,
+    pub total: u64,
+    pub available: u64,
+}
+
+impl Disk {
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+}
+
+/// Compressed RAM swap usage, as reported by `/sys/block/zram*/mm_stat`.
+#[derive(Debug, Default, Clone)]
+pub struct Zram {
+    /// The configured size of the devices.
+    pub disksize: u64,
+    /// Uncompressed size of the data stored.
+    pub data: u64,
+    /// Compressed size of the data stored.
+    pub compressed: u64,
+    /// RAM used to store the data, including allocator overhead.
+    pub mem_used: u64,
 }
 
 #[derive(Debug)]
@@ -43,11 +228,21 @@ pub struct Message {
     pub tick_duration: Duration,
     /// The time at the last update.
     pub time: Zoned,
+    /// All batteries combined, if there are any.
     pub battery: Option<Battery>,
+    /// Each battery, in the order the system enumerates them.
+    pub batteries: Vec<Battery>,
     pub network: Network,
     pub cpu: Cpu,
     pub temp: Temperature,
     pub memory: Memory,
+    pub system: SystemInfo,
+    /// Mounted filesystems, sorted by mount point. Empty unless
+    /// [`read_disks`] was called.
+    pub disks: Vec<Disk>,
+    /// Block device throughput, sorted by device name. Empty unless
+    /// [`read_disk_io`] was called.
+    pub disk_io: Vec<DiskIo>,
 }
 
 pub fn listen() -> watch::Receiver<Message> {
@@ -70,6 +265,58 @@ pub fn listen() -> watch::Receiver<Message> {
     SENDER.subscribe()
 }
 
+/// Whether anything shows the process count, which costs a walk of /proc.
+static COUNT_PROCESSES: AtomicBool = AtomicBool::new(false);
+
+/// Have [`SystemInfo::processes`] counted from now on.
+pub fn count_processes() {
+    COUNT_PROCESSES.store(true, Ordering::Relaxed);
+}
+
+/// Whether anything shows filesystem usage, which costs a `statvfs` per
+/// mount, and can block on unresponsive network mounts.
+static READ_DISKS: AtomicBool = AtomicBool::new(false);
+
+/// Have [`Message::disks`] read from now on.
+pub fn read_disks() {
+    READ_DISKS.store(true, Ordering::Relaxed);
+}
+
+/// Whether anything shows disk throughput, which costs a read of
+/// `/proc/diskstats` and a `/sys/block` lookup per device.
+static READ_DISK_IO: AtomicBool = AtomicBool::new(false);
+
+/// Have [`Message::disk_io`] read from now on.
+pub fn read_disk_io() {
+    READ_DISK_IO.store(true, Ordering::Relaxed);
+}
+
+/// Whether anything shows zram usage, which costs a scan of `/sys/block` and
+/// two reads per zram device.
+static READ_ZRAM: AtomicBool = AtomicBool::new(false);
+
+/// Have [`Memory::zram`] read from now on.
+pub fn read_zram() {
+    READ_ZRAM.store(true, Ordering::Relaxed);
+}
+
+/// The number of processes, from the numeric entries in `proc`.
+fn processes(proc: &Path) -> usize {
+    std::fs::read_dir(proc)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()))
+                })
+                .count()
+        })
+        .unwrap_or_default()
+}
+
 /// This producer currently produces anything that we produce on a 1-second
 /// tick.
 struct Producer {
@@ -77,17 +324,35 @@ struct Producer {
     system: System,
     networks: Networks,
     components: Components,
-    battery_manager: starship_battery::Manager,
+    disks: Disks,
+    diskstats: diskstats::Reader,
+    /// `None` if battery support failed to initialize.
+    battery_manager: Option<starship_battery::Manager>,
+    /// The last battery error logged, so that a persistent one isn't
+    /// repeated every tick.
+    battery_error: Option<String>,
+    /// Indices of the logical cores belonging to each physical core.
+    physical_cores: Vec<Vec<usize>>,
 }
 
 impl Default for Producer {
     fn default() -> Self {
+        let system =
+            System::new_with_specifics(RefreshKind::nothing().with_cpu(CpuRefreshKind::nothing()));
+        let physical_cores =
+            physical_cores(Path::new("/sys/devices/system/cpu"), system.cpus().len());
         Self {
             last_tick: Instant::now(),
-            system: System::default(),
+            system,
             networks: Networks::default(),
             components: Components::default(),
-            battery_manager: starship_battery::Manager::new().unwrap(),
+            disks: Disks::default(),
+            diskstats: diskstats::Reader::default(),
+            battery_manager: starship_battery::Manager::new()
+                .inspect_err(|e| eprintln!("tick: battery support unavailable: {e}"))
+                .ok(),
+            battery_error: None,
+            physical_cores,
         }
     }
 }
@@ -97,79 +362,407 @@ impl Producer {
         self.system.refresh_specifics(
             RefreshKind::nothing()
                 .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
-                .with_memory(MemoryRefreshKind::nothing().with_ram()),
+                .with_memory(MemoryRefreshKind::nothing().with_ram().with_swap()),
         );
         self.networks.refresh(true);
         self.components.refresh(true);
+        if READ_DISKS.load(Ordering::Relaxed) {
+            self.disks
+                .refresh_specifics(true, DiskRefreshKind::nothing().with_storage());
+        }
 
         let now = Instant::now();
         let tick_duration = now.duration_since(self.last_tick);
         self.last_tick = now;
 
+        let batteries = self.batteries();
         Message {
             time: Zoned::now(),
             tick_duration,
-            battery: self.battery(),
-            network: self.network(),
+            battery: Battery::combine(&batteries),
+            batteries,
+            network: self.network(tick_duration),
             cpu: self.cpu(),
             temp: self.temp(),
             memory: self.memory(),
+            system: self.system_info(),
+            disks: self.disks(),
+            disk_io: if READ_DISK_IO.load(Ordering::Relaxed) {
+                self.diskstats.read(tick_duration)
+            } else {
+                Vec::new()
+            },
         }
     }
 
-    fn battery(&self) -> Option<Battery> {
-        let battery = self.battery_manager.batteries().unwrap().next()?.unwrap();
-        Some(Battery {
-            charge: battery.state_of_charge().value,
-            state: battery.state(),
-        })
+    fn batteries(&mut self) -> Vec<Battery> {
+        let Some(manager) = &self.battery_manager else {
+            return Vec::new();
+        };
+        let mut error = None;
+        let batteries = match manager.batteries() {
+            Ok(batteries) => batteries
+                .filter_map(|battery| battery.inspect_err(|e| error = Some(e.to_string())).ok())
+                .map(|battery| Battery {
+                    name: match (battery.vendor(), battery.model()) {
+                        (Some(vendor), Some(model)) => Some(format!("{vendor} {model}")),
+                        (vendor, model) => vendor.or(model).map(str::to_string),
+                    },
+                    charge: battery.state_of_charge().get::<ratio::ratio>(),
+                    state: battery.state(),
+                    energy: battery.energy().get::<energy::watt_hour>(),
+                    energy_full: battery.energy_full().get::<energy::watt_hour>(),
+                    energy_full_design: battery.energy_full_design().get::<energy::watt_hour>(),
+                    power: battery.energy_rate().get::<power::watt>().abs(),
+                    time_to_full: battery
+                        .time_to_full()
+                        .and_then(|t| Duration::try_from_secs_f32(t.get::<time::second>()).ok()),
+                    time_to_empty: battery
+                        .time_to_empty()
+                        .and_then(|t| Duration::try_from_secs_f32(t.get::<time::second>()).ok()),
+                })
+                .collect(),
+            Err(e) => {
+                error = Some(e.to_string());
+                Vec::new()
+            }
+        };
+
+        if let Some(e) = &error
+            && error != self.battery_error
+        {
+            eprintln!("tick: failed to read batteries: {e}");
+        }
+        self.battery_error = error;
+        batteries
     }
 
-    fn network(&self) -> Network {
-        let (bytes_received, bytes_transmitted) = self
+    fn network(&self, tick_duration: Duration) -> Network {
+        let secs = tick_duration.as_secs_f64().max(f64::EPSILON);
+        let rate = |bytes: u64| (bytes as f64 / secs) as u64;
+
+        let mut interfaces: Vec<Interface> = self
             .networks
             .iter()
-            .filter_map(|(name, network)| {
-                if std::path::Path::new(&format!("/sys/class/net/{name}/device")).exists() {
-                    Some((network.received(), network.transmitted()))
-                } else {
-                    None
-                }
+            .map(|(name, network)| Interface {
+                name: name.clone(),
+                received: rate(network.received()),
+                transmitted: rate(network.transmitted()),
+                up: !matches!(
+                    network.operational_state(),
+                    InterfaceOperationalState::Down
+                        | InterfaceOperationalState::LowerLayerDown
+                        | InterfaceOperationalState::NotPresent
+                ),
+                physical: Path::new(&format!("/sys/class/net/{name}/device")).exists(),
+                addresses: network.ip_networks().iter().map(|ip| ip.addr).collect(),
             })
-            .fold((0, 0), |sum, (r, t)| (sum.0 + r, sum.1 + t));
+            .collect();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
 
-        Network {
-            bytes_received,
-            bytes_transmitted,
-        }
+        Network { interfaces }
     }
 
     fn cpu(&self) -> Cpu {
-        let (min, max) = self
+        let cores: Vec<f32> = self
             .system
             .cpus()
             .iter()
             .map(|cpu| cpu.cpu_usage() / 100.0)
-            .fold((f32::MAX, f32::MIN), |(min, max), cpu| {
-                (min.min(cpu), max.max(cpu))
-            });
+            .collect();
+        let (min, max) = cores.iter().fold((f32::MAX, f32::MIN), |(min, max), &cpu| {
+            (min.min(cpu), max.max(cpu))
+        });
         let avg = self.system.global_cpu_usage() / 100.0;
 
-        Cpu { min, avg, max }
+        let physical_cores = self
+            .physical_cores
+            .iter()
+            .map(|threads| {
+                let usages: Vec<f32> = threads
+                    .iter()
+                    .filter_map(|&i| cores.get(i))
+                    .copied()
+                    .collect();
+                usages.iter().sum::<f32>() / usages.len().max(1) as f32
+            })
+            .collect();
+
+        Cpu {
+            min,
+            avg,
+            max,
+            cores,
+            physical_cores,
+        }
     }
 
     fn temp(&self) -> Temperature {
-        let max = self
+        let sensors = self
             .components
             .iter()
-            .flat_map(|c| c.temperature())
-            .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-            .unwrap_or(-1.0);
-        Temperature { max }
+            .filter_map(|c| {
+                Some(Sensor {
+                    label: c.label().to_string(),
+                    temp: c.temperature().filter(|t| t.is_finite())?,
+                    critical: c.critical().filter(|t| t.is_finite() && *t > 0.0),
+                })
+            })
+            .collect();
+        Temperature { sensors }
+    }
+
+    fn system_info(&self) -> SystemInfo {
+        let load = System::load_average();
+        // The fourth field is "runnable/total" scheduling entities.
+        let running_threads = std::fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|s| {
+                let field = s.split_whitespace().nth(3)?;
+                field.split('/').next()?.parse().ok()
+            })
+            .unwrap_or_default();
+        SystemInfo {
+            load: [load.one, load.five, load.fifteen],
+            cpus: self.system.cpus().len(),
+            uptime: Duration::from_secs(System::uptime()),
+            processes: if COUNT_PROCESSES.load(Ordering::Relaxed) {
+                processes(Path::new("/proc"))
+            } else {
+                0
+            },
+            running_threads,
+        }
+    }
+
+    fn disks(&self) -> Vec<Disk> {
+        let mut disks: Vec<Disk> = self
+            .disks
+            .iter()
+            .map(|disk| Disk {
+                mount_point: disk.mount_point().to_path_buf(),
+                name: disk.name().to_string_lossy().into_owned(),
+                total: disk.total_space(),
+                available: disk.available_space(),
+            })
+            .collect();
+        disks.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+        disks
     }
 
     fn memory(&self) -> Memory {
-        let available = self.system.available_memory();
-        Memory { available }
+        Memory {
+            total: self.system.total_memory(),
+            available: self.system.available_memory(),
+            used: self.system.used_memory(),
+            swap_total: self.system.total_swap(),
+            swap_used: self.system.used_swap(),
+            zram: if READ_ZRAM.load(Ordering::Relaxed) {
+                zram()
+            } else {
+                None
+            },
+        }
+    }
+}
+
+fn zram() -> Option<Zram> {
+    let devices = std::fs::read_dir("/sys/block")
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("zram"));
+
+    let mut total: Option<Zram> = None;
+    for device in devices {
+        let path = device.path();
+        let read = |name| std::fs::read_to_string(path.join(name)).ok();
+        let Some(disksize) = read("disksize").and_then(|s| s.trim().parse::<u64>().ok()) else {
+            continue;
+        };
+        let Some((data, compressed, mem_used)) = read("mm_stat").as_deref().and_then(mm_stat)
+        else {
+            continue;
+        };
+
+        let sum = total.get_or_insert_default();
+        sum.disksize += disksize;
+        sum.data += data;
+        sum.compressed += compressed;
+        sum.mem_used += mem_used;
+    }
+    total
+}
+
+/// The first three fields of a zram device's `mm_stat`: orig_data_size,
+/// compr_data_size and mem_used_total. `None` if any of them is malformed.
+fn mm_stat(stat: &str) -> Option<(u64, u64, u64)> {
+    let mut fields = stat.split_whitespace().map(str::parse);
+    let mut next = || fields.next()?.ok();
+    Some((next()?, next()?, next()?))
+}
+
+/// Groups logical cores by the physical core they run on, using the topology
+/// the kernel exposes in sysfs under `cpus`. Groups are ordered by their first
+/// logical core. Cores without topology information are treated as their own
+/// physical core.
+fn physical_cores(cpus: &Path, n: usize) -> Vec<Vec<usize>> {
+    let read_id = |cpu: usize, name: &str| {
+        std::fs::read_to_string(cpus.join(format!("cpu{cpu}/topology/{name}")))
+            .ok()
+            .and_then(|s| s.trim().parse::<i64>().ok())
+    };
+
+    let mut keys = Vec::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for cpu in 0..n {
+        let key = (read_id(cpu, "physical_package_id"), read_id(cpu, "core_id"));
+        match keys.iter().position(|k| key.1.is_some() && *k == key) {
+            Some(i) => groups[i].push(cpu),
+            None => {
+                keys.push(key);
+                groups.push(vec![cpu]);
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(state: State, energy: f32, energy_full: f32, power: f32) -> Battery {
+        Battery {
+            state,
+            charge: energy / energy_full,
+            energy,
+            energy_full,
+            energy_full_design: 50.0,
+            power,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_mm_stat() {
+        assert_eq!(
+            mm_stat(
+                "  8192000  2048000  2301952        0  2301952       12        0     1234     5678\n"
+            ),
+            Some((8192000, 2048000, 2301952))
+        );
+        // A malformed field mustn't shift the others into its place.
+        assert_eq!(mm_stat("8192000 oops 2301952 0 2301952"), None);
+        assert_eq!(mm_stat("8192000 2048000"), None);
+    }
+
+    #[test]
+    fn groups_hardware_threads_by_core() {
+        let dir = std::env::temp_dir().join(format!("rustybar-cpus-{}", std::process::id()));
+        // Two packages; cpu0 and cpu2 share a core, as do cpu1 and cpu3.
+        // cpu4 has the same core ID as cpu0 but on the other package, and
+        // cpu5 has no topology at all.
+        for (cpu, package, core) in [(0, 0, 0), (1, 0, 1), (2, 0, 0), (3, 0, 1), (4, 1, 0)] {
+            let topology = dir.join(format!("cpu{cpu}/topology"));
+            std::fs::create_dir_all(&topology).unwrap();
+            std::fs::write(topology.join("physical_package_id"), format!("{package}\n")).unwrap();
+            std::fs::write(topology.join("core_id"), format!("{core}\n")).unwrap();
+        }
+
+        assert_eq!(
+            physical_cores(&dir, 6),
+            [vec![0, 2], vec![1, 3], vec![4], vec![5]]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn counts_process_entries() {
+        let dir = std::env::temp_dir().join(format!("rustybar-proc-{}", std::process::id()));
+        for entry in ["1", "42", "self", "sys", "1a"] {
+            std::fs::create_dir_all(dir.join(entry)).unwrap();
+        }
+        std::fs::write(dir.join("7"), "").unwrap();
+        std::fs::write(dir.join("loadavg"), "").unwrap();
+
+        // Threads live under each process, not at the top level.
+        std::fs::create_dir_all(dir.join("42/task/43")).unwrap();
+
+        assert_eq!(processes(&dir), 3);
+        assert_eq!(processes(&dir.join("missing")), 0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Batteries holding more than their full energy mustn't make the time
+    /// to full negative.
+    #[test]
+    fn combine_overfull() {
+        let combined = Battery::combine(&[
+            battery(State::Charging, 42.0, 40.0, 10.0),
+            battery(State::Charging, 10.0, 20.0, 10.0),
+        ])
+        .unwrap();
+        assert_eq!(combined.time_to_full, Some(Duration::from_secs(1440)));
+
+        let combined = Battery::combine(&[
+            battery(State::Charging, 45.0, 40.0, 10.0),
+            battery(State::Full, 20.0, 20.0, 0.0),
+        ])
+        .unwrap();
+        assert_eq!(combined.time_to_full, Some(Duration::ZERO));
+        assert_eq!(combined.time_to_empty, None);
+    }
+
+    /// A rate too small to divide by mustn't overflow the estimate.
+    #[test]
+    fn combine_negligible_rate() {
+        let combined = Battery::combine(&[
+            battery(State::Discharging, 10.0, 40.0, f32::MIN_POSITIVE),
+            battery(State::Full, 20.0, 20.0, 0.0),
+        ])
+        .unwrap();
+        assert_eq!(combined.state, State::Discharging);
+        assert_eq!(combined.time_to_empty, None);
+    }
+
+    #[test]
+    fn combine_nothing() {
+        assert!(Battery::combine(&[]).is_none());
+    }
+
+    #[test]
+    fn combine_weights_charge_by_capacity() {
+        let combined = Battery::combine(&[
+            battery(State::Discharging, 10.0, 40.0, 8.0),
+            battery(State::Full, 20.0, 20.0, 0.0),
+        ])
+        .unwrap();
+        assert_eq!(combined.state, State::Discharging);
+        assert_eq!(combined.charge, 0.5);
+        assert_eq!(combined.power, 8.0);
+        assert_eq!(combined.health(), 0.6);
+        assert_eq!(
+            combined.time_to_empty,
+            Some(Duration::from_secs(3 * 3600 + 45 * 60))
+        );
+        assert_eq!(combined.time_to_full, None);
+    }
+
+    /// One battery charging the other mustn't count as drawing power twice.
+    #[test]
+    fn combine_nets_opposing_rates() {
+        let combined = Battery::combine(&[
+            battery(State::Charging, 10.0, 40.0, 12.0),
+            battery(State::Discharging, 20.0, 20.0, 4.0),
+        ])
+        .unwrap();
+        assert_eq!(combined.state, State::Charging);
+        assert_eq!(combined.power, 8.0);
+        assert_eq!(
+            combined.time_to_full,
+            Some(Duration::from_secs(3 * 3600 + 45 * 60))
+        );
     }
 }
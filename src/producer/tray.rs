@@ -0,0 +1,983 @@
+//! System tray items, following the StatusNotifierItem spec on the session
+//! bus. We serve the StatusNotifierWatcher that items register with, unless
+//! something else already does, in which case we register with that one as a
+//! host. Item menus are read over DBusMenu.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::LazyLock,
+    time::Duration,
+};
+
+use futures::{
+    StreamExt, TryStreamExt, future,
+    stream::{self, BoxStream},
+};
+use iced::widget::{image, svg};
+use tokio::sync::{OnceCell, watch};
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::{self, DBusProxy, PropertiesProxy, RequestNameFlags, RequestNameReply},
+    message::{self, Header},
+    names::InterfaceName,
+    object_server::SignalEmitter,
+    zvariant::{OwnedValue, Value},
+};
+
+use crate::{producer::publish, util::icon};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+/// Where an item lives when it registers with just its bus name.
+const DEFAULT_ITEM_PATH: &str = "/StatusNotifierItem";
+
+#[derive(Debug, Default)]
+pub struct Message {
+    /// Every item, in the order they registered.
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Item {
+    /// The item's bus name and object path, e.g.
+    /// ":1.42/org/ayatana/NotificationItem/nm_applet".
+    pub address: String,
+    /// The application's name for the item, e.g. "nm-applet".
+    pub id: String,
+    pub title: String,
+    pub status: Status,
+    pub icon: Option<Icon>,
+    /// The item only offers its menu, so activating it should show that.
+    pub item_is_menu: bool,
+    pub menu: Option<Menu>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Not worth showing, e.g. a chat app with no unread messages.
+    Passive,
+    #[default]
+    Active,
+    NeedsAttention,
+}
+
+#[derive(Debug, Clone)]
+pub enum Icon {
+    Image(image::Handle),
+    Svg(svg::Handle),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Menu {
+    /// The object path of the item's DBusMenu.
+    pub path: String,
+    pub items: Vec<MenuItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    pub id: i32,
+    /// With mnemonic underscores removed.
+    pub label: String,
+    pub separator: bool,
+    pub enabled: bool,
+    pub visible: bool,
+    /// Whether a checkbox or radio item is checked, if the item is one.
+    pub checked: Option<bool>,
+    /// The items of its submenu, if it has one.
+    pub children: Vec<MenuItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Activate,
+    /// Usually sent on middle click.
+    SecondaryActivate,
+    /// Asks the item to show its own context menu, for items without a
+    /// DBusMenu.
+    ContextMenu,
+    /// Scrolls by this many steps, positive for up.
+    Scroll(i32),
+    /// Tells the menu at this path that it's about to be shown, so it can
+    /// update itself.
+    MenuOpened(String),
+    /// Clicks the item with this id in the menu at this path.
+    MenuClicked(String, i32),
+}
+
+#[zbus::proxy(
+    interface = "org.kde.StatusNotifierWatcher",
+    default_service = "org.kde.StatusNotifierWatcher",
+    default_path = "/StatusNotifierWatcher"
+)]
+trait StatusNotifierWatcher {
+    fn register_status_notifier_host(&self, service: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> zbus::Result<Vec<String>>;
+}
+
+#[zbus::proxy(interface = "org.kde.StatusNotifierItem")]
+trait StatusNotifierItem {
+    fn activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+    fn secondary_activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+    fn context_menu(&self, x: i32, y: i32) -> zbus::Result<()>;
+    fn scroll(&self, delta: i32, orientation: &str) -> zbus::Result<()>;
+}
+
+/// A menu item's id, properties and children.
+type Layout = (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>);
+
+#[zbus::proxy(interface = "com.canonical.dbusmenu")]
+trait DBusMenu {
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        property_names: &[&str],
+    ) -> zbus::Result<(u32, Layout)>;
+
+    fn event(&self, id: i32, event_id: &str, data: &Value<'_>, timestamp: u32) -> zbus::Result<()>;
+
+    fn about_to_show(&self, id: i32) -> zbus::Result<bool>;
+}
+
+pub fn listen() -> watch::Receiver<Message> {
+    static SENDER: LazyLock<watch::Sender<Message>> = LazyLock::new(|| {
+        let (sender, _) = watch::channel(Message::default());
+
+        let s = sender.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let result = match session().await {
+                    Ok(connection) => run(connection, &sender).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("tray: connection ended, reconnecting: {e}");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        s
+    });
+
+    SENDER.subscribe()
+}
+
+async fn session() -> zbus::Result<&'static Connection> {
+    static SESSION: OnceCell<Connection> = OnceCell::const_new();
+    SESSION.get_or_try_init(Connection::session).await
+}
+
+/// Sends `command` to the item at `address`.
+pub async fn command(address: String, command: Command) {
+    let result = async {
+        let connection = session().await?;
+        let (bus_name, path) = split_address(&address);
+        let item = async || {
+            StatusNotifierItemProxy::builder(connection)
+                .destination(bus_name)?
+                .path(path)?
+                .build()
+                .await
+        };
+        let menu = async |path: &str| {
+            DBusMenuProxy::builder(connection)
+                .destination(bus_name)?
+                .path(path.to_string())?
+                .build()
+                .await
+        };
+        match &command {
+            Command::Activate => item().await?.activate(0, 0).await,
+            Command::SecondaryActivate => item().await?.secondary_activate(0, 0).await,
+            Command::ContextMenu => item().await?.context_menu(0, 0).await,
+            Command::Scroll(delta) => item().await?.scroll(*delta, "vertical").await,
+            Command::MenuOpened(path) => menu(path).await?.about_to_show(0).await.map(|_| ()),
+            Command::MenuClicked(path, id) => {
+                let data = Value::from(0i32);
+                menu(path).await?.event(*id, "clicked", &data, 0).await
+            }
+        }
+    }
+    .await;
+    if let Err(e) = result {
+        eprintln!("tray: {command:?} failed for {address}: {e}");
+    }
+}
+
+/// The watcher, for when no one else is serving it.
+#[derive(Default)]
+struct Watcher {
+    items: Vec<String>,
+    hosts: Vec<String>,
+}
+
+#[zbus::interface(name = "org.kde.StatusNotifierWatcher")]
+impl Watcher {
+    async fn register_status_notifier_item(
+        &mut self,
+        service: &str,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        let sender = header.sender().map(|s| s.as_str()).unwrap_or_default();
+        let address = item_address(service, sender);
+        if !self.items.contains(&address) {
+            self.items.push(address.clone());
+            Self::status_notifier_item_registered(&emitter, &address).await?;
+            self.registered_status_notifier_items_changed(&emitter)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn register_status_notifier_host(
+        &mut self,
+        service: &str,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        if !self.hosts.iter().any(|h| h == service) {
+            self.hosts.push(service.to_string());
+            Self::status_notifier_host_registered(&emitter).await?;
+            self.is_status_notifier_host_registered_changed(&emitter)
+                .await?;
+        }
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> Vec<String> {
+        self.items.clone()
+    }
+
+    #[zbus(property)]
+    fn is_status_notifier_host_registered(&self) -> bool {
+        !self.hosts.is_empty()
+    }
+
+    #[zbus(property)]
+    fn protocol_version(&self) -> i32 {
+        0
+    }
+
+    #[zbus(signal)]
+    async fn status_notifier_item_registered(
+        emitter: &SignalEmitter<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_item_unregistered(
+        emitter: &SignalEmitter<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_registered(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+}
+
+/// Items register with either their bus name or, as Ayatana's library does,
+/// their object path. Either way, we keep both.
+fn item_address(service: &str, sender: &str) -> String {
+    if service.starts_with('/') {
+        format!("{sender}{service}")
+    } else if service.contains('/') {
+        service.to_string()
+    } else {
+        format!("{service}{DEFAULT_ITEM_PATH}")
+    }
+}
+
+fn split_address(address: &str) -> (&str, &str) {
+    match address.find('/') {
+        Some(i) => address.split_at(i),
+        None => (address, DEFAULT_ITEM_PATH),
+    }
+}
+
+/// Serves the watcher on `connection`, unless another process already does.
+/// Returns whether we're the watcher.
+async fn serve_watcher(connection: &Connection) -> zbus::Result<bool> {
+    connection
+        .object_server()
+        .at(WATCHER_PATH, Watcher::default())
+        .await?;
+    let reply = connection
+        .request_name_with_flags(WATCHER_NAME, RequestNameFlags::DoNotQueue.into())
+        .await?;
+    let serving = matches!(
+        reply,
+        RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner
+    );
+    if !serving {
+        connection
+            .object_server()
+            .remove::<Watcher, _>(WATCHER_PATH)
+            .await?;
+    }
+    Ok(serving)
+}
+
+/// Drops the items and hosts of a bus name that has gone away.
+async fn forget(connection: &Connection, name: &str) -> zbus::Result<()> {
+    let watcher = connection
+        .object_server()
+        .interface::<_, Watcher>(WATCHER_PATH)
+        .await?;
+    let emitter = watcher.signal_emitter();
+    let mut watcher = watcher.get_mut().await;
+
+    let (gone, kept) = watcher
+        .items
+        .drain(..)
+        .partition(|address| split_address(address).0 == name);
+    watcher.items = kept;
+    for address in &gone {
+        Watcher::status_notifier_item_unregistered(emitter, address).await?;
+    }
+    if !gone.is_empty() {
+        watcher
+            .registered_status_notifier_items_changed(emitter)
+            .await?;
+    }
+    watcher.hosts.retain(|host| host != name);
+    Ok(())
+}
+
+/// An item we've read, with the unique bus name its signals come from.
+struct Tracked {
+    owner: String,
+    item: Item,
+}
+
+/// Publishes the items, then again whenever one of them changes, appears or
+/// goes away. Only the item that changed is read again.
+async fn run(connection: &Connection, sender: &watch::Sender<Message>) -> zbus::Result<()> {
+    let items = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface(ITEM_INTERFACE)?
+        .build();
+    let menus = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface(MENU_INTERFACE)?
+        .build();
+    let watcher = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface(WATCHER_NAME)?
+        .build();
+    let mut signals = stream::select_all([
+        MessageStream::for_match_rule(items, connection, None)
+            .await?
+            .boxed(),
+        MessageStream::for_match_rule(menus, connection, None)
+            .await?
+            .boxed(),
+        MessageStream::for_match_rule(watcher, connection, None)
+            .await?
+            .boxed(),
+    ]);
+
+    let serving = serve_watcher(connection).await?;
+    let host = format!("org.kde.StatusNotifierHost-{}", std::process::id());
+    connection.request_name(host.as_str()).await?;
+    let watcher = StatusNotifierWatcherProxy::builder(connection)
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+        .await?;
+    watcher.register_status_notifier_host(&host).await?;
+
+    let mut tracked = Vec::new();
+    // The bus names of the items and hosts registered with us, each
+    // followed until it goes away.
+    let mut clients = HashSet::new();
+    let mut refresh = true;
+    loop {
+        if refresh {
+            let addresses = watcher.registered_status_notifier_items().await?;
+            tracked = update(connection, tracked, addresses).await;
+            if serving {
+                for name in registered(connection).await? {
+                    if !clients.contains(&name) {
+                        follow_client(connection, &name, &mut signals).await?;
+                        clients.insert(name);
+                    }
+                }
+            }
+        }
+        publish(
+            sender,
+            Message {
+                items: tracked.iter().map(|t| t.item.clone()).collect(),
+            },
+        );
+
+        let Some(signal) = signals.next().await else {
+            return Ok(());
+        };
+        let signal = signal?;
+        let header = signal.header();
+        let interface = header.interface().map(|i| i.as_str());
+        if interface == Some(ITEM_INTERFACE) || interface == Some(MENU_INTERFACE) {
+            let from = header.sender().map(|s| s.as_str());
+            let path = header.path().map(|p| p.as_str());
+            let mut known = false;
+            for t in tracked
+                .iter_mut()
+                .filter(|t| Some(t.owner.as_str()) == from)
+            {
+                let item_path = split_address(&t.item.address).1;
+                let menu_path = t.item.menu.as_ref().map(|m| m.path.as_str());
+                if Some(item_path) == path || menu_path == path {
+                    known = true;
+                    if let Ok(item) = read_item(connection, t.item.address.clone()).await {
+                        t.item = item;
+                    }
+                }
+            }
+            // Perhaps an item that couldn't be read when it registered.
+            refresh = !known;
+        } else if header.member().is_some_and(|m| m == "NameOwnerChanged") {
+            let (name, _, _): (String, String, String) = signal.body().deserialize()?;
+            forget(connection, &name).await?;
+            clients.remove(&name);
+            refresh = true;
+        } else {
+            refresh = true;
+        }
+    }
+}
+
+/// The bus names of the items and hosts registered with our watcher.
+async fn registered(connection: &Connection) -> zbus::Result<Vec<String>> {
+    let watcher = connection
+        .object_server()
+        .interface::<_, Watcher>(WATCHER_PATH)
+        .await?;
+    let watcher = watcher.get().await;
+    Ok(watcher
+        .items
+        .iter()
+        .map(|address| split_address(address).0.to_string())
+        .chain(watcher.hosts.iter().cloned())
+        .collect())
+}
+
+/// Adds the signal of `name` going away to `signals`, or forgets it right
+/// away if it's already gone.
+async fn follow_client(
+    connection: &Connection,
+    name: &str,
+    signals: &mut stream::SelectAll<BoxStream<'static, zbus::Result<zbus::Message>>>,
+) -> zbus::Result<()> {
+    let rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, name)?
+        .build();
+    let gone = MessageStream::for_match_rule(rule, connection, None)
+        .await?
+        .try_filter(|signal| {
+            let lost = signal
+                .body()
+                .deserialize::<(&str, &str, &str)>()
+                .is_ok_and(|(_, _, new_owner)| new_owner.is_empty());
+            future::ready(lost)
+        })
+        // The stream, and its match rule, are dropped once it's gone.
+        .take(1);
+    // It may have gone before the rule was in place.
+    if DBusProxy::new(connection)
+        .await?
+        .name_has_owner(name.try_into()?)
+        .await?
+    {
+        signals.push(gone.boxed());
+    } else {
+        forget(connection, name).await?;
+    }
+    Ok(())
+}
+
+/// The items at `addresses`, in that order, keeping those in `tracked`
+/// rather than reading them again.
+async fn update(
+    connection: &Connection,
+    tracked: Vec<Tracked>,
+    addresses: Vec<String>,
+) -> Vec<Tracked> {
+    let mut known: HashMap<String, Tracked> = tracked
+        .into_iter()
+        .map(|t| (t.item.address.clone(), t))
+        .collect();
+    let mut items = Vec::new();
+    for address in addresses {
+        if let Some(t) = known.remove(&address) {
+            items.push(t);
+            continue;
+        }
+        // Items come and go; one vanishing mid-read isn't an error.
+        let (bus_name, _) = split_address(&address);
+        let Ok(owner) = owner(connection, bus_name).await else {
+            continue;
+        };
+        if let Ok(item) = read_item(connection, address).await {
+            items.push(Tracked { owner, item });
+        }
+    }
+    items
+}
+
+/// The unique name behind `bus_name`, which sends the item's signals.
+async fn owner(connection: &Connection, bus_name: &str) -> zbus::Result<String> {
+    if bus_name.starts_with(':') {
+        return Ok(bus_name.to_string());
+    }
+    let owner = DBusProxy::new(connection)
+        .await?
+        .get_name_owner(bus_name.try_into()?)
+        .await?;
+    Ok(owner.to_string())
+}
+
+async fn read_item(connection: &Connection, address: String) -> zbus::Result<Item> {
+    let (bus_name, path) = split_address(&address);
+    let properties = PropertiesProxy::builder(connection)
+        .destination(bus_name)?
+        .path(path)?
+        .build()
+        .await?
+        .get_all(InterfaceName::from_static_str_unchecked(ITEM_INTERFACE))
+        .await?;
+    let string = |name: &str| {
+        properties
+            .get(name)
+            .and_then(|v| v.downcast_ref::<&str>().ok())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+
+    let status = match string("Status").as_deref() {
+        Some("Passive") => Status::Passive,
+        Some("NeedsAttention") => Status::NeedsAttention,
+        _ => Status::Active,
+    };
+    let theme_path = string("IconThemePath").map(PathBuf::from);
+    let icon_from = |name: &str, pixmap: &str| {
+        string(name)
+            .and_then(|name| icon::find(&name, theme_path.as_deref()))
+            .map(|path| match path.extension().and_then(|e| e.to_str()) {
+                Some("svg") => Icon::Svg(svg::Handle::from_path(path)),
+                _ => Icon::Image(image::Handle::from_path(path)),
+            })
+            .or_else(|| {
+                let pixmaps = properties.get(pixmap)?.try_clone().ok()?.try_into().ok()?;
+                pixmap_icon(pixmaps)
+            })
+    };
+    let icon = match status {
+        Status::NeedsAttention => icon_from("AttentionIconName", "AttentionIconPixmap"),
+        _ => None,
+    }
+    .or_else(|| icon_from("IconName", "IconPixmap"));
+
+    let menu = match properties
+        .get("Menu")
+        .and_then(|v| v.downcast_ref::<zbus::zvariant::ObjectPath>().ok())
+    {
+        Some(menu) if menu.as_str() != "/" => read_menu(connection, bus_name, menu.as_str())
+            .await
+            .inspect_err(|e| eprintln!("tray: couldn't read the menu of {address}: {e}"))
+            .ok(),
+        _ => None,
+    };
+
+    let id = string("Id").unwrap_or_default();
+    Ok(Item {
+        title: string("Title").unwrap_or_else(|| id.clone()),
+        id,
+        status,
+        icon,
+        item_is_menu: properties
+            .get("ItemIsMenu")
+            .and_then(|v| v.downcast_ref::<bool>().ok())
+            .unwrap_or(false),
+        menu,
+        address,
+    })
+}
+
+/// The largest of an item's pixmaps, which come as ARGB in network byte
+/// order.
+fn pixmap_icon(pixmaps: Vec<(i32, i32, Vec<u8>)>) -> Option<Icon> {
+    let (width, height, argb) = pixmaps
+        .into_iter()
+        .filter(|(w, h, data)| {
+            *w > 0
+                && *h > 0
+                && (*w as usize)
+                    .checked_mul(*h as usize)
+                    .and_then(|n| n.checked_mul(4))
+                    == Some(data.len())
+        })
+        .max_by_key(|(w, h, _)| i64::from(*w) * i64::from(*h))?;
+    let rgba = argb
+        .chunks_exact(4)
+        .flat_map(|p| [p[1], p[2], p[3], p[0]])
+        .collect::<Vec<_>>();
+    Some(Icon::Image(image::Handle::from_rgba(
+        width as u32,
+        height as u32,
+        rgba,
+    )))
+}
+
+async fn read_menu(connection: &Connection, bus_name: &str, path: &str) -> zbus::Result<Menu> {
+    let (_revision, (_, _, children)) = DBusMenuProxy::builder(connection)
+        .destination(bus_name)?
+        .path(path)?
+        .build()
+        .await?
+        .get_layout(0, -1, &[])
+        .await?;
+    Ok(Menu {
+        path: path.to_string(),
+        items: children.iter().filter_map(|c| parse_menu_item(c)).collect(),
+    })
+}
+
+/// Parses a `(ia{sv}av)` layout, with its children.
+fn parse_menu_item(layout: &Value) -> Option<MenuItem> {
+    let layout = match layout {
+        Value::Value(inner) => inner,
+        layout => layout,
+    };
+    let Value::Structure(layout) = layout else {
+        return None;
+    };
+    let [id, properties, children] = layout.fields() else {
+        return None;
+    };
+    let properties: HashMap<String, OwnedValue> = properties.try_clone().ok()?.try_into().ok()?;
+    let string = |name: &str| {
+        properties
+            .get(name)
+            .and_then(|v| v.downcast_ref::<&str>().ok())
+    };
+    let flag = |name: &str| {
+        properties
+            .get(name)
+            .and_then(|v| v.downcast_ref::<bool>().ok())
+            .unwrap_or(true)
+    };
+    let checked = match string("toggle-type") {
+        Some("checkmark" | "radio") => Some(
+            properties
+                .get("toggle-state")
+                .and_then(|v| v.downcast_ref::<i32>().ok())
+                == Some(1),
+        ),
+        _ => None,
+    };
+    let children = match children {
+        Value::Array(children) => children.iter().filter_map(parse_menu_item).collect(),
+        _ => Vec::new(),
+    };
+
+    Some(MenuItem {
+        id: id.downcast_ref::<i32>().ok()?,
+        label: strip_mnemonics(string("label").unwrap_or_default()),
+        separator: string("type") == Some("separator"),
+        enabled: flag("enabled"),
+        visible: flag("visible"),
+        checked,
+        children,
+    })
+}
+
+/// Drops the underscores that mark access keys, keeping escaped ones ("__").
+fn strip_mnemonics(label: &str) -> String {
+    let mut stripped = String::with_capacity(label.len());
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        if c == '_' {
+            if let Some(next) = chars.next() {
+                stripped.push(next);
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use zbus::{
+        interface,
+        zvariant::{ObjectPath, StructureBuilder},
+    };
+
+    use super::*;
+    use crate::util::test_bus::TestBus;
+
+    #[test]
+    fn normalizes_item_addresses() {
+        assert_eq!(
+            item_address("org.kde.StatusNotifierItem-7-1", ":1.5"),
+            "org.kde.StatusNotifierItem-7-1/StatusNotifierItem"
+        );
+        assert_eq!(
+            item_address("/org/ayatana/NotificationItem/nm", ":1.5"),
+            ":1.5/org/ayatana/NotificationItem/nm"
+        );
+        assert_eq!(
+            split_address(":1.5/org/ayatana/NotificationItem/nm"),
+            (":1.5", "/org/ayatana/NotificationItem/nm")
+        );
+    }
+
+    #[test]
+    fn strips_mnemonics() {
+        assert_eq!(strip_mnemonics("_Quit"), "Quit");
+        assert_eq!(strip_mnemonics("snake__case"), "snake_case");
+        assert_eq!(strip_mnemonics("Plain"), "Plain");
+    }
+
+    const ITEM_PATH: &str = "/org/ayatana/NotificationItem/fake";
+    const MENU_PATH: &str = "/MenuBar";
+
+    struct FakeItem {
+        title: String,
+        /// How many times the item has been read.
+        reads: AtomicUsize,
+    }
+
+    #[interface(name = "org.kde.StatusNotifierItem")]
+    impl FakeItem {
+        #[zbus(property)]
+        fn id(&self) -> &str {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            "fake"
+        }
+
+        #[zbus(property)]
+        fn title(&self) -> String {
+            self.title.clone()
+        }
+
+        #[zbus(property)]
+        fn status(&self) -> &str {
+            "NeedsAttention"
+        }
+
+        #[zbus(property)]
+        fn icon_pixmap(&self) -> Vec<(i32, i32, Vec<u8>)> {
+            // A small icon, and a bigger one of two opaque red pixels.
+            vec![
+                (1, 1, vec![0xff, 0, 0xff, 0]),
+                (2, 1, [0xff, 0xff, 0, 0].repeat(2)),
+            ]
+        }
+
+        #[zbus(property)]
+        fn menu(&self) -> ObjectPath<'_> {
+            ObjectPath::from_static_str_unchecked(MENU_PATH)
+        }
+
+        #[zbus(property)]
+        fn item_is_menu(&self) -> bool {
+            false
+        }
+
+        #[zbus(signal)]
+        async fn new_title(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+    }
+
+    struct FakeMenu;
+
+    fn entry(
+        id: i32,
+        properties: &[(&str, Value<'static>)],
+        children: Vec<OwnedValue>,
+    ) -> OwnedValue {
+        let properties: HashMap<String, OwnedValue> = properties
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.try_to_owned().unwrap()))
+            .collect();
+        Value::from(
+            StructureBuilder::new()
+                .add_field(id)
+                .add_field(properties)
+                .add_field(children)
+                .build()
+                .unwrap(),
+        )
+        .try_into()
+        .unwrap()
+    }
+
+    #[interface(name = "com.canonical.dbusmenu")]
+    impl FakeMenu {
+        fn get_layout(
+            &self,
+            _parent_id: i32,
+            _depth: i32,
+            _properties: Vec<String>,
+        ) -> (u32, Layout) {
+            let children = vec![
+                entry(
+                    1,
+                    &[
+                        ("label", Value::from("_Mute")),
+                        ("toggle-type", Value::from("checkmark")),
+                        ("toggle-state", Value::from(1i32)),
+                    ],
+                    vec![],
+                ),
+                entry(2, &[("type", Value::from("separator"))], vec![]),
+                entry(
+                    3,
+                    &[
+                        ("label", Value::from("Status")),
+                        ("children-display", Value::from("submenu")),
+                    ],
+                    vec![entry(
+                        4,
+                        &[
+                            ("label", Value::from("Away")),
+                            ("enabled", Value::from(false)),
+                        ],
+                        vec![],
+                    )],
+                ),
+            ];
+            (7, (0, HashMap::new(), children))
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_fake_item() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+
+        let host = bus.connect().await;
+        let (sender, mut receiver) = watch::channel(Message::default());
+        let h = host.clone();
+        tokio::spawn(async move { run(&h, &sender).await });
+
+        let mut next = async |check: fn(&[Item]) -> bool| {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    receiver.changed().await.unwrap();
+                    let msg = receiver.borrow_and_update();
+                    if check(&msg.items) {
+                        return msg.items.clone();
+                    }
+                }
+            })
+            .await
+            .expect("tray change was not published")
+        };
+        // Wait for the host to be watching, before the item registers.
+        next(|_| true).await;
+
+        let service = bus
+            .builder()
+            .serve_at(
+                ITEM_PATH,
+                FakeItem {
+                    title: "Fake".into(),
+                    reads: AtomicUsize::new(0),
+                },
+            )
+            .unwrap()
+            .serve_at(MENU_PATH, FakeMenu)
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        service
+            .call_method(
+                Some(WATCHER_NAME),
+                WATCHER_PATH,
+                Some(WATCHER_NAME),
+                "RegisterStatusNotifierItem",
+                &ITEM_PATH,
+            )
+            .await
+            .unwrap();
+
+        let items = next(|items| !items.is_empty()).await;
+        let item = &items[0];
+        assert_eq!(
+            item.address,
+            format!("{}{ITEM_PATH}", service.unique_name().unwrap())
+        );
+        assert_eq!(item.id, "fake");
+        assert_eq!(item.title, "Fake");
+        assert_eq!(item.status, Status::NeedsAttention);
+        assert!(matches!(item.icon, Some(Icon::Image(_))));
+
+        let menu = item.menu.as_ref().unwrap();
+        assert_eq!(menu.path, MENU_PATH);
+        let labels: Vec<_> = menu.items.iter().map(|i| i.label.as_str()).collect();
+        assert_eq!(labels, ["Mute", "", "Status"]);
+        assert_eq!(menu.items[0].checked, Some(true));
+        assert!(menu.items[1].separator);
+        assert_eq!(menu.items[2].checked, None);
+        assert_eq!(menu.items[2].children[0].label, "Away");
+        assert!(!menu.items[2].children[0].enabled);
+
+        let fake = service
+            .object_server()
+            .interface::<_, FakeItem>(ITEM_PATH)
+            .await
+            .unwrap();
+        fake.get_mut().await.title = "Renamed".into();
+        FakeItem::new_title(fake.signal_emitter()).await.unwrap();
+        next(|items| items.first().is_some_and(|i| i.title == "Renamed")).await;
+        // Read once on registering and once on the signal, however much
+        // else happened on the bus.
+        assert_eq!(fake.get().await.reads.load(Ordering::Relaxed), 2);
+
+        drop(fake);
+        drop(service);
+        next(|items| items.is_empty()).await;
+    }
+
+    #[test]
+    fn converts_pixmaps_to_rgba() {
+        let Some(Icon::Image(handle)) = pixmap_icon(vec![(1, 1, vec![0x80, 1, 2, 3])]) else {
+            panic!("no icon");
+        };
+        let image::Handle::Rgba {
+            width,
+            height,
+            pixels,
+            ..
+        } = handle
+        else {
+            panic!("not rgba");
+        };
+        assert_eq!((width, height), (1, 1));
+        assert_eq!(pixels.as_ref(), [1, 2, 3, 0x80]);
+    }
+
+    #[test]
+    fn skips_oversized_pixmaps() {
+        assert!(pixmap_icon(vec![(i32::MAX, i32::MAX, vec![0; 4])]).is_none());
+        assert!(pixmap_icon(vec![(65536, 65536, vec![])]).is_none());
+    }
+}
@@ -0,0 +1,389 @@
+//! Batteries as reported by UPower over the system bus. Unlike the tick's
+//! battery readings, this updates as soon as UPower does (e.g. on plugging
+//! in), and includes peripherals like mice and headsets.
+
+use std::{collections::HashMap, sync::LazyLock, time::Duration};
+
+use futures::StreamExt;
+use starship_battery::State;
+use tokio::sync::watch;
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::PropertiesProxy,
+    message,
+    names::InterfaceName,
+    zvariant::{OwnedObjectPath, OwnedValue, Value},
+};
+
+use crate::producer::{publish, tick::Battery};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const SERVICE: &str = "org.freedesktop.UPower";
+const PATH: &str = "/org/freedesktop/UPower";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+#[derive(Debug, Default)]
+pub struct Message {
+    /// Every device with a battery, in the order UPower lists them.
+    pub devices: Vec<Device>,
+}
+
+impl Message {
+    /// Batteries that power the system, as opposed to peripherals.
+    pub fn system_batteries(&self) -> impl Iterator<Item = &Battery> {
+        self.devices
+            .iter()
+            .filter(|d| d.power_supply && d.kind == DeviceKind::Battery)
+            .map(|d| &d.battery)
+    }
+
+    /// Batteries of peripherals, such as mice and headsets.
+    pub fn peripherals(&self) -> impl Iterator<Item = &Device> {
+        self.devices.iter().filter(|d| !d.power_supply)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub kind: DeviceKind,
+    /// Whether the device powers the system.
+    pub power_supply: bool,
+    pub battery: Battery,
+}
+
+/// UPower's device types, less those that never have batteries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Battery,
+    Ups,
+    Mouse,
+    Keyboard,
+    Phone,
+    Tablet,
+    GamingInput,
+    Pen,
+    Touchpad,
+    Headset,
+    Speakers,
+    Headphones,
+    Other,
+}
+
+impl DeviceKind {
+    fn from_upower(kind: u32) -> Option<Self> {
+        Some(match kind {
+            // Unknown, line power and monitors don't carry a battery.
+            0 | 1 | 4 => return None,
+            2 => DeviceKind::Battery,
+            3 => DeviceKind::Ups,
+            5 => DeviceKind::Mouse,
+            6 => DeviceKind::Keyboard,
+            8 => DeviceKind::Phone,
+            10 => DeviceKind::Tablet,
+            12 => DeviceKind::GamingInput,
+            13 => DeviceKind::Pen,
+            14 => DeviceKind::Touchpad,
+            17 => DeviceKind::Headset,
+            18 => DeviceKind::Speakers,
+            19 => DeviceKind::Headphones,
+            _ => DeviceKind::Other,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceKind::Battery => "battery",
+            DeviceKind::Ups => "UPS",
+            DeviceKind::Mouse => "mouse",
+            DeviceKind::Keyboard => "keyboard",
+            DeviceKind::Phone => "phone",
+            DeviceKind::Tablet => "tablet",
+            DeviceKind::GamingInput => "controller",
+            DeviceKind::Pen => "pen",
+            DeviceKind::Touchpad => "touchpad",
+            DeviceKind::Headset => "headset",
+            DeviceKind::Speakers => "speakers",
+            DeviceKind::Headphones => "headphones",
+            DeviceKind::Other => "device",
+        }
+    }
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower"
+)]
+trait UPower {
+    fn enumerate_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+pub fn listen() -> watch::Receiver<Message> {
+    static SENDER: LazyLock<watch::Sender<Message>> = LazyLock::new(|| {
+        let (sender, _) = watch::channel(Message::default());
+
+        let s = sender.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let result = match Connection::system().await {
+                    Ok(connection) => run(&connection, &sender).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("upower: connection ended, reconnecting: {e}");
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        s
+    });
+
+    SENDER.subscribe()
+}
+
+/// Publishes UPower's devices, then again after every signal it sends:
+/// devices being added or removed, and their properties changing.
+async fn run(connection: &Connection, sender: &watch::Sender<Message>) -> zbus::Result<()> {
+    let rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .path_namespace(PATH)?
+        .build();
+    let mut signals = MessageStream::for_match_rule(rule, connection, None).await?;
+
+    loop {
+        publish(sender, read(connection).await?);
+        if signals.next().await.is_none() {
+            return Ok(());
+        }
+    }
+}
+
+async fn read(connection: &Connection) -> zbus::Result<Message> {
+    let upower = UPowerProxy::new(connection).await?;
+    let mut devices = Vec::new();
+    for path in upower.enumerate_devices().await? {
+        let properties = PropertiesProxy::builder(connection)
+            .destination(SERVICE)?
+            .path(path)?
+            .build()
+            .await?;
+        let all = properties
+            .get_all(InterfaceName::from_static_str_unchecked(DEVICE_INTERFACE))
+            .await?;
+        devices.extend(parse_device(&all));
+    }
+    Ok(Message { devices })
+}
+
+fn parse_device(properties: &HashMap<String, OwnedValue>) -> Option<Device> {
+    fn get<'a, T>(properties: &'a HashMap<String, OwnedValue>, name: &str) -> Option<T>
+    where
+        T: TryFrom<&'a Value<'a>>,
+        <T as TryFrom<&'a Value<'a>>>::Error: Into<zbus::zvariant::Error>,
+    {
+        properties.get(name)?.downcast_ref().ok()
+    }
+    let f64 = |name| get::<f64>(properties, name).unwrap_or_default() as f32;
+    let seconds = |name| {
+        get::<i64>(properties, name)
+            .filter(|&s| s > 0)
+            .map(|s| Duration::from_secs(s as u64))
+    };
+
+    let kind = DeviceKind::from_upower(get(properties, "Type")?)?;
+    if !get::<bool>(properties, "IsPresent").unwrap_or(true) {
+        return None;
+    }
+
+    let state = match get::<u32>(properties, "State")? {
+        1 => State::Charging,
+        2 | 6 => State::Discharging,
+        3 => State::Empty,
+        4 => State::Full,
+        _ => State::Unknown,
+    };
+    let name = [get::<&str>(properties, "Vendor"), get(properties, "Model")]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Some(Device {
+        kind,
+        power_supply: get(properties, "PowerSupply").unwrap_or(false),
+        battery: Battery {
+            name: (!name.is_empty()).then_some(name),
+            charge: f64("Percentage") / 100.0,
+            state,
+            energy: f64("Energy"),
+            energy_full: f64("EnergyFull"),
+            energy_full_design: f64("EnergyFullDesign"),
+            power: f64("EnergyRate").abs(),
+            time_to_full: seconds("TimeToFull"),
+            time_to_empty: seconds("TimeToEmpty"),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::interface;
+
+    use super::*;
+    use crate::util::test_bus::TestBus;
+
+    struct FakeUPower {
+        devices: Vec<OwnedObjectPath>,
+    }
+
+    #[interface(name = "org.freedesktop.UPower")]
+    impl FakeUPower {
+        fn enumerate_devices(&self) -> Vec<OwnedObjectPath> {
+            self.devices.clone()
+        }
+    }
+
+    struct FakeDevice {
+        kind: u32,
+        power_supply: bool,
+        percentage: f64,
+        state: u32,
+        model: String,
+    }
+
+    #[interface(name = "org.freedesktop.UPower.Device")]
+    impl FakeDevice {
+        #[zbus(property, name = "Type")]
+        fn kind(&self) -> u32 {
+            self.kind
+        }
+        #[zbus(property)]
+        fn power_supply(&self) -> bool {
+            self.power_supply
+        }
+        #[zbus(property)]
+        fn percentage(&self) -> f64 {
+            self.percentage
+        }
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            self.state
+        }
+        #[zbus(property)]
+        fn model(&self) -> String {
+            self.model.clone()
+        }
+        #[zbus(property)]
+        fn energy(&self) -> f64 {
+            self.percentage / 2.0
+        }
+        #[zbus(property)]
+        fn energy_full(&self) -> f64 {
+            50.0
+        }
+        #[zbus(property)]
+        fn energy_rate(&self) -> f64 {
+            -10.0
+        }
+        #[zbus(property)]
+        fn time_to_empty(&self) -> i64 {
+            if self.state == 2 { 3600 } else { 0 }
+        }
+    }
+
+    fn device(kind: u32, power_supply: bool, percentage: f64, model: &str) -> FakeDevice {
+        FakeDevice {
+            kind,
+            power_supply,
+            percentage,
+            state: 2,
+            model: model.into(),
+        }
+    }
+
+    /// Serves a fake UPower with a laptop battery, a mouse and AC power on a
+    /// private bus, and follows it like the real producer would.
+    #[tokio::test]
+    async fn follows_fake_upower() {
+        let Some(bus) = TestBus::start() else {
+            return;
+        };
+
+        let paths: Vec<OwnedObjectPath> = ["battery_BAT0", "mouse_0", "line_power_AC"]
+            .iter()
+            .map(|d| format!("{PATH}/devices/{d}").try_into().unwrap())
+            .collect();
+        let service = bus
+            .builder()
+            .name(SERVICE)
+            .unwrap()
+            .serve_at(
+                PATH,
+                FakeUPower {
+                    devices: paths.clone(),
+                },
+            )
+            .unwrap()
+            .serve_at(&paths[0], device(2, true, 40.0, "Laptop"))
+            .unwrap()
+            .serve_at(&paths[1], device(5, false, 80.0, "Mouse"))
+            .unwrap()
+            .serve_at(&paths[2], device(1, true, 0.0, ""))
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+
+        let client = bus.connect().await;
+        let (sender, mut receiver) = watch::channel(Message::default());
+        tokio::spawn(async move { run(&client, &sender).await });
+
+        receiver.changed().await.unwrap();
+        {
+            let msg = receiver.borrow_and_update();
+            let batteries: Vec<_> = msg.system_batteries().collect();
+            assert_eq!(batteries.len(), 1);
+            assert_eq!(batteries[0].name.as_deref(), Some("Laptop"));
+            assert_eq!(batteries[0].charge, 0.4);
+            assert_eq!(batteries[0].state, State::Discharging);
+            assert_eq!(batteries[0].power, 10.0);
+            assert_eq!(batteries[0].time_to_empty, Some(Duration::from_secs(3600)));
+            let peripherals: Vec<_> = msg.peripherals().collect();
+            assert_eq!(peripherals.len(), 1);
+            assert_eq!(peripherals[0].kind, DeviceKind::Mouse);
+        }
+
+        // Plugging in changes the battery's state; the producer must pick
+        // that up from the signal, with no polling.
+        let battery = service
+            .object_server()
+            .interface::<_, FakeDevice>(&paths[0])
+            .await
+            .unwrap();
+        battery.get_mut().await.state = 1;
+        battery
+            .get()
+            .await
+            .state_changed(battery.signal_emitter())
+            .await
+            .unwrap();
+
+        let charging = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                receiver.changed().await.unwrap();
+                let msg = receiver.borrow_and_update();
+                if let Some(b) = msg.system_batteries().next()
+                    && b.state == State::Charging
+                {
+                    return b.time_to_empty;
+                }
+            }
+        })
+        .await
+        .expect("state change was not published");
+        assert_eq!(charging, None);
+    }
+}
@@ -0,0 +1,474 @@
+//! Wireless link status, read straight from the kernel: SSID and frequency
+//! over nl80211 (generic netlink), and signal strength from
+//! `/proc/net/wireless`, falling back to nl80211's station info.
+
+use std::{sync::LazyLock, time::Duration};
+
+use netlink_sys::{Socket, SocketAddr, protocols::NETLINK_GENERIC};
+use tokio::sync::watch;
+
+use crate::producer::publish;
+
+const INTERVAL: Duration = Duration::from_secs(1);
+
+const NLMSG_HDR_LEN: usize = 16;
+const GENL_HDR_LEN: usize = 4;
+
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+const NL80211_CMD_GET_INTERFACE: u8 = 5;
+const NL80211_CMD_GET_STATION: u8 = 17;
+const NL80211_ATTR_IFINDEX: u16 = 3;
+const NL80211_ATTR_IFNAME: u16 = 4;
+const NL80211_ATTR_IFTYPE: u16 = 5;
+const NL80211_ATTR_STA_INFO: u16 = 21;
+const NL80211_ATTR_WIPHY_FREQ: u16 = 38;
+const NL80211_ATTR_SSID: u16 = 52;
+const NL80211_IFTYPE_STATION: u32 = 2;
+const NL80211_STA_INFO_SIGNAL: u16 = 7;
+
+/// Nested attributes set this flag in their type.
+const NLA_TYPE_MASK: u16 = 0x3fff;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Wifi {
+    pub interface: String,
+    /// The connected network's name, or `None` when not associated.
+    pub ssid: Option<String>,
+    /// Signal level in dBm.
+    pub signal: Option<f32>,
+    /// Operating frequency in MHz.
+    pub frequency: Option<u32>,
+}
+
+impl Wifi {
+    /// Signal quality from 0.0 to 1.0, scaling -90 dBm (unusable) to -30 dBm
+    /// (excellent) linearly.
+    pub fn quality(&self) -> Option<f32> {
+        self.signal.map(|dbm| ((dbm + 90.0) / 60.0).clamp(0.0, 1.0))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Message {
+    /// Wireless station interfaces, sorted by name.
+    pub interfaces: Vec<Wifi>,
+}
+
+/// Polls on a blocking thread of its own, so that a slow netlink reply
+/// doesn't hold up the tick.
+pub fn listen() -> watch::Receiver<Message> {
+    static SENDER: LazyLock<watch::Sender<Message>> = LazyLock::new(|| {
+        let (sender, _) = watch::channel(Message::default());
+
+        let s = sender.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut reader = Reader::default();
+            loop {
+                let interfaces = reader.read();
+                if sender.borrow().interfaces != interfaces {
+                    publish(&sender, Message { interfaces });
+                }
+                std::thread::sleep(INTERVAL);
+            }
+        });
+        s
+    });
+
+    SENDER.subscribe()
+}
+
+/// Queries wireless interfaces. Keeps its netlink socket open across reads,
+/// and reopens it after errors.
+#[derive(Default)]
+struct Reader {
+    nl80211: Option<Nl80211>,
+}
+
+impl Reader {
+    /// All wireless station interfaces, sorted by name.
+    fn read(&mut self) -> Vec<Wifi> {
+        let proc = std::fs::read_to_string("/proc/net/wireless")
+            .map(|s| parse_proc_wireless(&s))
+            .unwrap_or_default();
+
+        let mut wifis = match self.query() {
+            Ok(wifis) => wifis,
+            Err(e) => {
+                if self.nl80211.take().is_some() {
+                    eprintln!("wifi: nl80211 query failed: {e}");
+                }
+                // Without nl80211, /proc still tells us about signal.
+                proc.iter()
+                    .map(|(interface, signal)| Wifi {
+                        interface: interface.clone(),
+                        signal: Some(*signal),
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+        };
+
+        for wifi in &mut wifis {
+            if let Some((_, signal)) = proc.iter().find(|(name, _)| *name == wifi.interface) {
+                wifi.signal = Some(*signal);
+            }
+        }
+        wifis.sort_by(|a, b| a.interface.cmp(&b.interface));
+        wifis
+    }
+
+    fn query(&mut self) -> std::io::Result<Vec<Wifi>> {
+        let nl80211 = match &mut self.nl80211 {
+            Some(nl80211) => nl80211,
+            None => self.nl80211.insert(Nl80211::connect()?),
+        };
+
+        let mut wifis = Vec::new();
+        for (ifindex, mut wifi) in nl80211.interfaces()? {
+            if wifi.ssid.is_some() {
+                wifi.signal = nl80211.station_signal(ifindex)?;
+            }
+            wifis.push(wifi);
+        }
+        Ok(wifis)
+    }
+}
+
+/// Parses `/proc/net/wireless` into (interface, signal level in dBm) pairs.
+///
+/// The format is two header lines, then one line per interface:
+/// ` wlan0: 0000   54.  -56.  -256        0      0      0      0     51        0`
+fn parse_proc_wireless(contents: &str) -> Vec<(String, f32)> {
+    contents
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(':')?;
+            // Fields: status, link quality, signal level, noise, ...
+            let level = rest.split_whitespace().nth(2)?;
+            let level: f32 = level.trim_end_matches('.').parse().ok()?;
+            // Drivers reporting in dBm give negative levels; anything else is
+            // an unscaled quality value we can't interpret.
+            (level < 0.0).then(|| (name.trim().to_string(), level))
+        })
+        .collect()
+}
+
+struct Nl80211 {
+    socket: Socket,
+    family: u16,
+    seq: u32,
+}
+
+impl Nl80211 {
+    fn connect() -> std::io::Result<Self> {
+        let socket = Socket::new(NETLINK_GENERIC)?;
+        let mut this = Nl80211 {
+            socket,
+            family: GENL_ID_CTRL,
+            seq: 0,
+        };
+
+        let name = attr(CTRL_ATTR_FAMILY_NAME, b"nl80211\0");
+        let replies = this.request(GENL_ID_CTRL, CTRL_CMD_GETFAMILY, 1, 0, &name)?;
+        this.family = replies
+            .iter()
+            .flat_map(|payload| attrs(payload))
+            .find(|(ty, _)| *ty == CTRL_ATTR_FAMILY_ID)
+            .and_then(|(_, value)| Some(u16::from_ne_bytes(value.get(..2)?.try_into().ok()?)))
+            .ok_or_else(|| std::io::Error::other("nl80211 family not found"))?;
+        Ok(this)
+    }
+
+    /// Station interfaces and their index, with SSID and frequency filled in.
+    fn interfaces(&mut self) -> std::io::Result<Vec<(u32, Wifi)>> {
+        let replies = self.request(self.family, NL80211_CMD_GET_INTERFACE, 0, NLM_F_DUMP, &[])?;
+        Ok(replies.iter().filter_map(|p| parse_interface(p)).collect())
+    }
+
+    /// The signal of the access point `ifindex` is associated with.
+    fn station_signal(&mut self, ifindex: u32) -> std::io::Result<Option<f32>> {
+        let index = attr(NL80211_ATTR_IFINDEX, &ifindex.to_ne_bytes());
+        let replies = self.request(self.family, NL80211_CMD_GET_STATION, 0, NLM_F_DUMP, &index)?;
+        Ok(replies.iter().find_map(|p| parse_station_signal(p)))
+    }
+
+    /// Sends a generic netlink request and collects the attribute payloads
+    /// of all replies, following multipart dumps to their end.
+    fn request(
+        &mut self,
+        family: u16,
+        cmd: u8,
+        version: u8,
+        flags: u16,
+        body: &[u8],
+    ) -> std::io::Result<Vec<Vec<u8>>> {
+        self.seq = self.seq.wrapping_add(1);
+        let len = NLMSG_HDR_LEN + GENL_HDR_LEN + body.len();
+        let mut msg = Vec::with_capacity(len);
+        msg.extend_from_slice(&(len as u32).to_ne_bytes());
+        msg.extend_from_slice(&family.to_ne_bytes());
+        msg.extend_from_slice(&(NLM_F_REQUEST | flags).to_ne_bytes());
+        msg.extend_from_slice(&self.seq.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&[cmd, version, 0, 0]);
+        msg.extend_from_slice(body);
+        self.socket.send_to(&msg, &SocketAddr::new(0, 0), 0)?;
+
+        let mut replies = Vec::new();
+        loop {
+            let (buf, _) = self.socket.recv_from_full()?;
+            for reply in parse_messages(&buf, self.seq) {
+                match reply {
+                    Reply::Payload(payload) if flags & NLM_F_DUMP == 0 => return Ok(vec![payload]),
+                    Reply::Payload(payload) => replies.push(payload),
+                    Reply::Done => return Ok(replies),
+                    Reply::Error(0) => return Ok(replies),
+                    Reply::Error(errno) => return Err(std::io::Error::from_raw_os_error(-errno)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Reply {
+    /// The attributes of a generic netlink message, after its headers.
+    Payload(Vec<u8>),
+    Done,
+    /// A negated errno, or 0 for an acknowledgement.
+    Error(i32),
+}
+
+/// Splits a datagram into the netlink messages answering request `seq`.
+fn parse_messages(buf: &[u8], seq: u32) -> Vec<Reply> {
+    let mut replies = Vec::new();
+    let mut rest = buf;
+    while rest.len() >= NLMSG_HDR_LEN {
+        let len = u32::from_ne_bytes(rest[0..4].try_into().unwrap()) as usize;
+        if len < NLMSG_HDR_LEN || len > rest.len() {
+            break;
+        }
+        let ty = u16::from_ne_bytes(rest[4..6].try_into().unwrap());
+        let msg_seq = u32::from_ne_bytes(rest[8..12].try_into().unwrap());
+        let body = &rest[NLMSG_HDR_LEN..len];
+
+        if msg_seq == seq {
+            match ty {
+                NLMSG_DONE => replies.push(Reply::Done),
+                NLMSG_ERROR => {
+                    let errno = body
+                        .get(..4)
+                        .map_or(0, |b| i32::from_ne_bytes(b.try_into().unwrap()));
+                    replies.push(Reply::Error(errno));
+                }
+                _ if body.len() >= GENL_HDR_LEN => {
+                    replies.push(Reply::Payload(body[GENL_HDR_LEN..].to_vec()))
+                }
+                _ => {}
+            }
+        }
+        rest = &rest[align(len).min(rest.len())..];
+    }
+    replies
+}
+
+fn parse_interface(payload: &[u8]) -> Option<(u32, Wifi)> {
+    let mut ifindex = None;
+    let mut iftype = None;
+    let mut wifi = Wifi::default();
+    for (ty, value) in attrs(payload) {
+        match ty {
+            NL80211_ATTR_IFINDEX => ifindex = read_u32(value),
+            NL80211_ATTR_IFTYPE => iftype = read_u32(value),
+            NL80211_ATTR_IFNAME => wifi.interface = read_string(value),
+            NL80211_ATTR_SSID => wifi.ssid = Some(String::from_utf8_lossy(value).into_owned()),
+            NL80211_ATTR_WIPHY_FREQ => wifi.frequency = read_u32(value),
+            _ => {}
+        }
+    }
+    (iftype == Some(NL80211_IFTYPE_STATION)).then_some((ifindex?, wifi))
+}
+
+fn parse_station_signal(payload: &[u8]) -> Option<f32> {
+    let (_, info) = attrs(payload).find(|(ty, _)| *ty == NL80211_ATTR_STA_INFO)?;
+    let (_, signal) = attrs(info).find(|(ty, _)| *ty == NL80211_STA_INFO_SIGNAL)?;
+    Some(*signal.first()? as i8 as f32)
+}
+
+/// Iterates over netlink attributes as (type, value) pairs.
+fn attrs(mut buf: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if buf.len() < 4 {
+            return None;
+        }
+        let len = u16::from_ne_bytes(buf[0..2].try_into().unwrap()) as usize;
+        let ty = u16::from_ne_bytes(buf[2..4].try_into().unwrap()) & NLA_TYPE_MASK;
+        if len < 4 || len > buf.len() {
+            return None;
+        }
+        let value = &buf[4..len];
+        buf = &buf[align(len).min(buf.len())..];
+        Some((ty, value))
+    })
+}
+
+/// Encodes one netlink attribute, padded to alignment.
+fn attr(ty: u16, value: &[u8]) -> Vec<u8> {
+    let len = 4 + value.len();
+    let mut buf = Vec::with_capacity(align(len));
+    buf.extend_from_slice(&(len as u16).to_ne_bytes());
+    buf.extend_from_slice(&ty.to_ne_bytes());
+    buf.extend_from_slice(value);
+    buf.resize(align(len), 0);
+    buf
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_u32(value: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(value.get(..4)?.try_into().ok()?))
+}
+
+fn read_string(value: &[u8]) -> String {
+    let end = value.iter().position(|&b| b == 0).unwrap_or(value.len());
+    String::from_utf8_lossy(&value[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_WIRELESS: &str = "\
+Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE
+ face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22
+wlp3s0: 0000   54.  -56.  -256        0      0      0      0     51        0
+ wlan1: 0000    0.    0.     0        0      0      0      0      0        0
+";
+
+    #[test]
+    fn parses_proc_wireless() {
+        assert_eq!(
+            parse_proc_wireless(PROC_WIRELESS),
+            [("wlp3s0".to_string(), -56.0)]
+        );
+        assert_eq!(parse_proc_wireless(""), []);
+    }
+
+    /// Builds a netlink message as the kernel would send it.
+    fn message(ty: u16, seq: u32, body: &[u8]) -> Vec<u8> {
+        let len = NLMSG_HDR_LEN + body.len();
+        let mut msg = Vec::new();
+        msg.extend_from_slice(&(len as u32).to_ne_bytes());
+        msg.extend_from_slice(&ty.to_ne_bytes());
+        msg.extend_from_slice(&0x2u16.to_ne_bytes()); // NLM_F_MULTI
+        msg.extend_from_slice(&seq.to_ne_bytes());
+        msg.extend_from_slice(&1234u32.to_ne_bytes());
+        msg.extend_from_slice(body);
+        msg.resize(align(len), 0);
+        msg
+    }
+
+    fn genl(cmd: u8, attrs: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = vec![cmd, 1, 0, 0];
+        for a in attrs {
+            body.extend_from_slice(a);
+        }
+        body
+    }
+
+    /// A GET_INTERFACE dump with a connected station, a monitor interface, a
+    /// reply to a stale request, and the terminating DONE.
+    #[test]
+    fn parses_mocked_interface_dump() {
+        let station = genl(
+            7,
+            &[
+                attr(NL80211_ATTR_IFINDEX, &3u32.to_ne_bytes()),
+                attr(NL80211_ATTR_IFNAME, b"wlp3s0\0"),
+                attr(NL80211_ATTR_IFTYPE, &NL80211_IFTYPE_STATION.to_ne_bytes()),
+                attr(NL80211_ATTR_SSID, b"Caf\xc3\xa9 Wi-Fi"),
+                attr(NL80211_ATTR_WIPHY_FREQ, &5180u32.to_ne_bytes()),
+            ],
+        );
+        let monitor = genl(
+            7,
+            &[
+                attr(NL80211_ATTR_IFINDEX, &4u32.to_ne_bytes()),
+                attr(NL80211_ATTR_IFNAME, b"mon0\0"),
+                attr(NL80211_ATTR_IFTYPE, &6u32.to_ne_bytes()),
+            ],
+        );
+
+        let mut buf = message(0x1c, 9, &station);
+        buf.extend(message(0x1c, 8, &monitor));
+        buf.extend(message(0x1c, 9, &monitor));
+        buf.extend(message(NLMSG_DONE, 9, &0u32.to_ne_bytes()));
+
+        let replies = parse_messages(&buf, 9);
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[2], Reply::Done);
+
+        let interfaces: Vec<_> = replies
+            .iter()
+            .filter_map(|r| match r {
+                Reply::Payload(p) => parse_interface(p),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            interfaces,
+            [(
+                3,
+                Wifi {
+                    interface: "wlp3s0".into(),
+                    ssid: Some("Café Wi-Fi".into()),
+                    signal: None,
+                    frequency: Some(5180),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn parses_mocked_station_signal() {
+        let mut info = attr(1, &0u32.to_ne_bytes());
+        info.extend(attr(NL80211_STA_INFO_SIGNAL, &[(-61i8) as u8]));
+        let mut nested = attr(NL80211_ATTR_STA_INFO, &info);
+        // The kernel marks nested attributes with NLA_F_NESTED.
+        nested[2..4].copy_from_slice(&(NL80211_ATTR_STA_INFO | 0x8000).to_ne_bytes());
+
+        let payload = [attr(NL80211_ATTR_IFINDEX, &3u32.to_ne_bytes()), nested].concat();
+        assert_eq!(parse_station_signal(&payload), Some(-61.0));
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let buf = message(NLMSG_ERROR, 1, &(-19i32).to_ne_bytes());
+        assert_eq!(parse_messages(&buf, 1), [Reply::Error(-19)]);
+    }
+
+    #[test]
+    fn quality_scales_signal() {
+        let wifi = |signal| Wifi {
+            signal,
+            ..Default::default()
+        };
+        assert_eq!(wifi(Some(-90.0)).quality(), Some(0.0));
+        assert_eq!(wifi(Some(-60.0)).quality(), Some(0.5));
+        assert_eq!(wifi(Some(-20.0)).quality(), Some(1.0));
+        assert_eq!(wifi(None).quality(), None);
+    }
+}
@@ -1,3 +1,10 @@
 pub mod bytes;
 pub mod color;
+pub mod glob;
+pub mod graph;
+pub mod icon;
+pub mod notify;
 pub mod overflow_row;
+pub mod template;
+#[cfg(test)]
+pub mod test_bus;
@@ -0,0 +1,52 @@
+use regex::Regex;
+
+/// A shell-style pattern for matching names, such as network interfaces or
+/// block devices. `*` matches any run of characters and `?` any single one;
+/// everything else matches literally.
+#[derive(Clone, Debug)]
+pub struct Glob(Regex);
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        let mut re = String::from("^");
+        for c in pattern.chars() {
+            match c {
+                '*' => re.push_str(".*"),
+                '?' => re.push('.'),
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        re.push('$');
+        Glob(Regex::new(&re).expect("escaped glob is a valid regex"))
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.0.is_match(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_whole_name() {
+        assert!(Glob::new("wlan0").is_match("wlan0"));
+        assert!(!Glob::new("wlan").is_match("wlan0"));
+        assert!(!Glob::new("lan0").is_match("wlan0"));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(Glob::new("wl*").is_match("wlp3s0"));
+        assert!(Glob::new("wl*").is_match("wl"));
+        assert!(Glob::new("eth?").is_match("eth1"));
+        assert!(!Glob::new("eth?").is_match("eth10"));
+    }
+
+    #[test]
+    fn regex_syntax_is_literal() {
+        assert!(Glob::new("br.lan").is_match("br.lan"));
+        assert!(!Glob::new("br.lan").is_match("br-lan"));
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use iced::{
+    Element, Length, Point, Rectangle, Renderer, Theme, mouse,
+    widget::canvas::{self, Canvas, Geometry, Path, Stroke},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{consumer::IcedMessage, util::color::Colormap};
+
+/// A fixed-capacity buffer of the most recent samples of a metric, oldest
+/// first.
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    values: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl TimeSeries {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            values: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Appends `value`, dropping the oldest sample once full.
+    pub fn push(&mut self, value: f32) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.values.len() == self.capacity {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.values.iter().copied()
+    }
+
+    /// The smallest and largest sample, ignoring NaNs.
+    pub fn range(&self) -> Option<(f32, f32)> {
+        self.iter()
+            .filter(|v| !v.is_nan())
+            .fold(None, |range, v| match range {
+                None => Some((v, v)),
+                Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+            })
+    }
+}
+
+/// Records `value` of every message `receiver` sees into a new time series.
+///
+/// Consumers render from a shared `&self`, and not necessarily once per
+/// message, so the samples are collected by a background task instead.
+pub fn record<T, F>(
+    mut receiver: watch::Receiver<T>,
+    capacity: usize,
+    value: F,
+) -> Arc<Mutex<TimeSeries>>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&T) -> f32 + Send + 'static,
+{
+    let series = Arc::new(Mutex::new(TimeSeries::new(capacity)));
+    series
+        .lock()
+        .unwrap()
+        .push(value(&receiver.borrow_and_update()));
+
+    let s = series.clone();
+    tokio::spawn(async move {
+        while receiver.changed().await.is_ok() {
+            let v = value(&receiver.borrow_and_update());
+            s.lock().unwrap().push(v);
+        }
+    });
+    series
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct GraphConfig {
+    pub width: f32,
+    pub height: f32,
+    /// Number of samples shown; one per tick.
+    pub samples: usize,
+    #[serde(default)]
+    pub style: GraphStyle,
+    /// The value at the bottom of the graph. When unset, the module's default
+    /// is used, or the smallest sample shown if it has none.
+    #[serde(default)]
+    pub min: Option<f32>,
+    /// The value at the top of the graph. When unset, the module's default is
+    /// used, or the largest sample shown if it has none.
+    #[serde(default)]
+    pub max: Option<f32>,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphStyle {
+    /// A line through the samples.
+    Line,
+    /// The area under the samples, filled.
+    #[default]
+    Area,
+}
+
+/// A history graph of one metric, as configured by a module's `graph` option.
+pub struct Graph {
+    config: GraphConfig,
+    series: Arc<Mutex<TimeSeries>>,
+    min: Option<f32>,
+    max: Option<f32>,
+}
+
+impl Graph {
+    /// Starts recording `value` of every message from `receiver`.
+    pub fn new<T, F>(config: GraphConfig, receiver: watch::Receiver<T>, value: F) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&T) -> f32 + Send + 'static,
+    {
+        let series = record(receiver, config.samples, value);
+        Self {
+            config,
+            series,
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Sets the range used for bounds the config leaves unset, instead of
+    /// autoscaling to the samples.
+    pub fn default_range(mut self, min: Option<f32>, max: Option<f32>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Draws the graph, coloring each sample through `colormap`.
+    pub fn view<'a>(&'a self, colormap: &'a Colormap) -> Element<'a, IcedMessage> {
+        let series = self.series.lock().unwrap();
+        let (min, max) = range(
+            &series,
+            self.config.min.or(self.min),
+            self.config.max.or(self.max),
+        );
+        let sparkline = Sparkline {
+            values: series.iter().collect(),
+            samples: series.capacity(),
+            min,
+            max,
+            style: self.config.style,
+            colormap,
+        };
+
+        Canvas::new(sparkline)
+            .width(Length::Fixed(self.config.width))
+            .height(Length::Fixed(self.config.height))
+            .into()
+    }
+}
+
+/// The range a graph of `series` spans, filling whichever of `min` and `max`
+/// are unset from the samples. Never empty, so values can be normalized.
+fn range(series: &TimeSeries, min: Option<f32>, max: Option<f32>) -> (f32, f32) {
+    let (lo, hi) = series.range().unwrap_or((0.0, 1.0));
+    let min = min.unwrap_or(lo);
+    let max = max.unwrap_or(hi);
+    if max > min {
+        (min, max)
+    } else {
+        (min, min + 1.0)
+    }
+}
+
+/// An area or line graph of a time series, with the newest sample on the
+/// right.
+struct Sparkline<'a> {
+    values: Vec<f32>,
+    samples: usize,
+    min: f32,
+    max: f32,
+    style: GraphStyle,
+    colormap: &'a Colormap,
+}
+
+impl canvas::Program<IcedMessage> for Sparkline<'_> {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &(),
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+
+        let step = bounds.width / self.samples.saturating_sub(1).max(1) as f32;
+        let x0 = bounds.width - step * self.values.len().saturating_sub(1) as f32;
+        let point = |i: usize| {
+            let v = (self.values[i] - self.min) / (self.max - self.min);
+            Point::new(
+                x0 + step * i as f32,
+                bounds.height * (1.0 - v.clamp(0.0, 1.0)),
+            )
+        };
+
+        // Each segment takes the color of the newer sample it leads to, so
+        // the graph reads like a row of colormapped bars.
+        for i in 1..self.values.len() {
+            let color = self.colormap.map(self.values[i]);
+            let (a, b) = (point(i - 1), point(i));
+            match self.style {
+                GraphStyle::Line => {
+                    let segment = Path::line(a, b);
+                    frame.stroke(
+                        &segment,
+                        Stroke::default().with_color(color).with_width(1.0),
+                    );
+                }
+                GraphStyle::Area => {
+                    let area = Path::new(|p| {
+                        p.move_to(Point::new(a.x, bounds.height));
+                        p.line_to(a);
+                        p.line_to(b);
+                        p.line_to(Point::new(b.x, bounds.height));
+                        p.close();
+                    });
+                    frame.fill(&area, color);
+                }
+            }
+        }
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(capacity: usize, values: &[f32]) -> TimeSeries {
+        let mut s = TimeSeries::new(capacity);
+        for &v in values {
+            s.push(v);
+        }
+        s
+    }
+
+    #[test]
+    fn drops_oldest_when_full() {
+        let s = series(3, &[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(s.iter().collect::<Vec<_>>(), [3.0, 4.0, 5.0]);
+        assert_eq!(s.len(), 3);
+    }
+
+    #[test]
+    fn autoscales_unset_bounds() {
+        let s = series(10, &[4.0, 2.0, f32::NAN, 8.0]);
+        assert_eq!(range(&s, None, None), (2.0, 8.0));
+        assert_eq!(range(&s, Some(0.0), None), (0.0, 8.0));
+        assert_eq!(range(&s, None, Some(100.0)), (2.0, 100.0));
+    }
+
+    /// A flat or empty series must still yield a non-empty range, or
+    /// normalizing its samples would divide by zero.
+    #[test]
+    fn range_is_never_empty() {
+        assert_eq!(range(&series(10, &[3.0, 3.0]), None, None), (3.0, 4.0));
+        assert_eq!(range(&series(10, &[]), None, None), (0.0, 1.0));
+        assert_eq!(range(&series(10, &[5.0]), None, Some(2.0)), (5.0, 6.0));
+    }
+}
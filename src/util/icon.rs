@@ -0,0 +1,266 @@
+//! Icon lookup through the installed icon themes, and the packages apps
+//! come in.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+};
+
+use crate::producer;
+
+/// Icons looked for, by name and theme path, and what was found. Searching
+/// themes means walking a lot of directories, so we remember.
+type Key = (String, Option<PathBuf>);
+static FOUND: LazyLock<Mutex<HashMap<Key, Option<PathBuf>>>> = LazyLock::new(Default::default);
+
+/// Finds the file for a named icon. `name` may be an absolute path, and
+/// `theme_path` a directory to look in first. After that, we look through
+/// every installed theme, without regard to which one is configured,
+/// preferring scalable icons and then larger ones.
+pub fn find(name: &str, theme_path: Option<&Path>) -> Option<PathBuf> {
+    if name.starts_with('/') {
+        return Path::new(name).is_file().then(|| PathBuf::from(name));
+    }
+    let key = (name.to_string(), theme_path.map(Path::to_path_buf));
+    if let Some(found) = FOUND.lock().unwrap().get(&key) {
+        return found.clone();
+    }
+    let found = theme_path
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(icon_dirs())
+        .find_map(|dir| find_in(&dir, name));
+    FOUND.lock().unwrap().insert(key, found.clone());
+    found
+}
+
+/// Like [`find`], but without blocking, for use while drawing. Icons not
+/// looked for yet are looked for on another thread, and the bars woken once
+/// that's done; until then, there's no icon.
+pub fn find_in_background(name: &str) -> Option<PathBuf> {
+    if name.starts_with('/') {
+        return find(name, None);
+    }
+    static SEARCHING: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+    let key = (name.to_string(), None);
+    if let Some(found) = FOUND.lock().unwrap().get(&key) {
+        return found.clone();
+    }
+    in_background(&SEARCHING, name, |name| {
+        find(name, None);
+    });
+    None
+}
+
+/// Icons found for app IDs, which may come from the app's package as well as
+/// the themes.
+static APP_ICONS: LazyLock<Mutex<HashMap<String, Option<PathBuf>>>> =
+    LazyLock::new(Default::default);
+
+/// The icon for a Wayland app ID, which themes usually name it by, or
+/// otherwise by the app ID in lower case. Failing that, we look in the
+/// package that installed the app, see [`find_in_package`]. Looked for in
+/// the background, as with [`find_in_background`].
+pub fn app_icon(app_id: &str) -> Option<PathBuf> {
+    static SEARCHING: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+    if let Some(found) = APP_ICONS.lock().unwrap().get(app_id) {
+        return found.clone();
+    }
+    in_background(&SEARCHING, app_id, |app_id| {
+        let found = find(app_id, None)
+            .or_else(|| find(&app_id.to_lowercase(), None))
+            .or_else(|| find_in_package(app_id));
+        APP_ICONS.lock().unwrap().insert(app_id.to_string(), found);
+    });
+    None
+}
+
+/// Runs `search` for `name` on another thread, unless one is running for it
+/// already, and wakes the bars once it's done.
+fn in_background(searching: &'static Mutex<HashSet<String>>, name: &str, search: fn(&str)) {
+    if searching.lock().unwrap().insert(name.to_string()) {
+        let name = name.to_string();
+        std::thread::spawn(move || {
+            search(&name);
+            searching.lock().unwrap().remove(&name);
+            producer::wake();
+        });
+    }
+}
+
+/// Looks in the package that installed the executable `app_id`, for systems
+/// like NixOS that keep each package in a directory of its own.
+fn find_in_package(app_id: &str) -> Option<PathBuf> {
+    let executable = std::env::var_os("PATH")
+        .iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join(app_id))
+        .find(|path| path.is_file())?;
+    // The package is two levels up from its bin/<app>.
+    let executable = std::fs::canonicalize(executable).ok()?;
+    package_icon(executable.ancestors().nth(2)?, app_id)
+}
+
+/// Looks for `app_id`'s icon in the package at `package`: in its icon themes
+/// and pixmaps, and in "lib/<app_id>/logo", where kitty keeps its icon.
+fn package_icon(package: &Path, app_id: &str) -> Option<PathBuf> {
+    [
+        "share/icons".into(),
+        "share/pixmaps".into(),
+        format!("lib/{app_id}/logo"),
+    ]
+    .iter()
+    .find_map(|dir| find_in(&package.join(dir), app_id))
+}
+
+fn icon_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .map(|dir| dir.join("icons"))
+        .chain(home.map(|h| h.join(".icons")))
+        .chain(
+            [
+                "/usr/share/pixmaps",
+                // NixOS's system profile, in case it's missing from the data
+                // dirs.
+                "/run/current-system/sw/share/icons",
+                "/run/current-system/sw/share/pixmaps",
+            ]
+            .map(PathBuf::from),
+        )
+        .collect()
+}
+
+/// Looks for `name` in `dir` itself, and three levels below it, where themes
+/// keep icons, like "hicolor/48x48/apps" or "breeze/apps/48".
+fn find_in(dir: &Path, name: &str) -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    let mut dirs = vec![(dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if depth < 3 {
+                    dirs.push((path, depth + 1));
+                }
+            } else if (depth == 0 || depth == 3)
+                && path.file_stem().is_some_and(|s| s == name)
+                && path.extension().is_some_and(|e| e == "png" || e == "svg")
+            {
+                candidates.push(path);
+            }
+        }
+    }
+    candidates
+        .into_iter()
+        .max_by_key(|path| icon_size(dir, path))
+}
+
+/// Scalable icons count as the largest, and others by the first size in
+/// their path, e.g. 48 for "hicolor/48x48/apps/firefox.png".
+fn icon_size(dir: &Path, path: &Path) -> u32 {
+    if path.extension().is_some_and(|e| e == "svg") {
+        return u32::MAX;
+    }
+    path.strip_prefix(dir)
+        .unwrap_or(path)
+        .components()
+        .find_map(|c| {
+            let c = c.as_os_str().to_str()?;
+            let digits = c.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(c.len());
+            c[..digits].parse().ok()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_scalable_then_larger_icons() {
+        let dir = std::env::temp_dir().join(format!("rustybar-icons-{}", std::process::id()));
+        for icon in [
+            "hicolor/16x16/apps/app.png",
+            "hicolor/48x48/apps/app.png",
+            "hicolor/48x48/apps/other.png",
+            "pixmap.png",
+        ] {
+            let path = dir.join(icon);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+
+        assert_eq!(
+            find_in(&dir, "app"),
+            Some(dir.join("hicolor/48x48/apps/app.png"))
+        );
+        assert_eq!(find_in(&dir, "pixmap"), Some(dir.join("pixmap.png")));
+        assert_eq!(find_in(&dir, "missing"), None);
+
+        let scalable = dir.join("hicolor/scalable/apps/app.svg");
+        std::fs::create_dir_all(scalable.parent().unwrap()).unwrap();
+        std::fs::write(&scalable, b"").unwrap();
+        assert_eq!(find_in(&dir, "app"), Some(scalable));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn finds_icons_in_packages() {
+        let dir = std::env::temp_dir().join(format!("rustybar-package-{}", std::process::id()));
+        for icon in [
+            "share/icons/hicolor/256x256/apps/app.png",
+            "share/pixmaps/pixmap.png",
+            "lib/kitty/logo/kitty.png",
+        ] {
+            let path = dir.join(icon);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+
+        assert_eq!(
+            package_icon(&dir, "app"),
+            Some(dir.join("share/icons/hicolor/256x256/apps/app.png"))
+        );
+        assert_eq!(
+            package_icon(&dir, "pixmap"),
+            Some(dir.join("share/pixmaps/pixmap.png"))
+        );
+        assert_eq!(
+            package_icon(&dir, "kitty"),
+            Some(dir.join("lib/kitty/logo/kitty.png"))
+        );
+        assert_eq!(package_icon(&dir, "missing"), None);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn searches_in_background() {
+        let name = "rustybar-no-such-icon";
+        assert_eq!(find_in_background(name), None);
+        let key = (name.to_string(), None);
+        for _ in 0..500 {
+            if FOUND.lock().unwrap().contains_key(&key) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("the search didn't finish");
+    }
+}
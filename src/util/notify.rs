@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+
+use zbus::{Connection, zvariant::Value};
+
+/// How urgent a desktop notification is, per the notification spec.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low = 0,
+    #[default]
+    Normal = 1,
+    Critical = 2,
+}
+
+/// Sends a desktop notification over the session bus.
+pub async fn notify(summary: &str, body: &str, urgency: Urgency) -> zbus::Result<()> {
+    let connection = Connection::session().await?;
+    let hints = HashMap::from([("urgency", Value::U8(urgency as u8))]);
+    connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                "rustybar",
+                0u32,
+                "",
+                summary,
+                body,
+                Vec::<&str>::new(),
+                hints,
+                -1i32,
+            ),
+        )
+        .await?;
+    Ok(())
+}
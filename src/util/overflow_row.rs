@@ -737,8 +737,8 @@ mod tests {
         let renderer = Rec::default();
         let limits = Limits::new(Size::ZERO, Size::new(avail, 100.0));
         let node = {
-            let mut r = Rec::default();
-            element.as_widget_mut().layout(tree, &mut r, &limits)
+            let r = Rec::default();
+            element.as_widget_mut().layout(tree, &r, &limits)
         };
         let layout = Layout::new(&node);
         let viewport = Rectangle::new(Point::ORIGIN, Size::new(avail, 100.0));
@@ -781,7 +781,8 @@ mod tests {
         // Each frame: (workspaces as (id,width), focus). Ids are stable
         // identities; we shuffle order, change focus, and add/remove to stress
         // the tree diff across frames — exactly what overflow + niri events do.
-        let frames: Vec<(Vec<(usize, f32)>, Option<usize>)> = vec![
+        type Frame = (Vec<(usize, f32)>, Option<usize>);
+        let frames: Vec<Frame> = vec![
             (vec![(0, 52.0), (1, 52.0), (2, 64.0)], Some(2)),
             (vec![(0, 52.0), (1, 52.0), (2, 64.0)], Some(1)),
             (vec![(0, 52.0), (1, 52.0), (2, 64.0)], Some(0)),
@@ -1022,8 +1023,8 @@ mod tests {
         let mut tree = Tree::new(&element);
         let limits = Limits::new(Size::ZERO, Size::new(avail, 100.0));
         let node = {
-            let mut r = RecText::default();
-            element.as_widget_mut().layout(&mut tree, &mut r, &limits)
+            let r = RecText::default();
+            element.as_widget_mut().layout(&mut tree, &r, &limits)
         };
         let layout = Layout::new(&node);
         let width = node.size().width;
@@ -0,0 +1,62 @@
+/// Fills `{name}` placeholders in `template` with `value(name)`. Placeholders
+/// `value` doesn't know are kept as written, so typos show up in the bar.
+/// `{{` and `}}` are a literal brace.
+pub fn fill(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        match tail.find('}').filter(|_| tail.starts_with('{')) {
+            Some(end) => {
+                let name = &tail[1..end];
+                match value(name) {
+                    Some(v) => out.push_str(&v),
+                    None => out.push_str(&tail[..=end]),
+                }
+                rest = &tail[end + 1..];
+            }
+            None => {
+                out.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(name: &str) -> Option<String> {
+        match name {
+            "a" => Some("1".into()),
+            "long_name" => Some("two".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn fills_placeholders() {
+        assert_eq!(fill("{a} and {long_name}!", value), "1 and two!");
+        assert_eq!(fill("no placeholders", value), "no placeholders");
+    }
+
+    #[test]
+    fn keeps_unknown_and_unclosed() {
+        assert_eq!(fill("{b} {a", value), "{b} {a");
+        assert_eq!(fill("a} {a}", value), "a} 1");
+    }
+
+    #[test]
+    fn escaped_braces() {
+        assert_eq!(fill("{{a}} {{{a}}}", value), "{a} {1}");
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+};
+
+/// A private D-Bus daemon for tests, killed on drop.
+pub struct TestBus {
+    daemon: Child,
+    address: String,
+}
+
+impl TestBus {
+    /// Starts a session-style bus. Returns `None` when `dbus-daemon` isn't
+    /// installed, so tests can skip instead of failing.
+    pub fn start() -> Option<Self> {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .inspect_err(|e| eprintln!("test_bus: not running D-Bus tests: {e}"))
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+
+        Some(TestBus {
+            daemon,
+            address: address.trim().to_string(),
+        })
+    }
+
+    pub async fn connect(&self) -> zbus::Connection {
+        self.builder().build().await.unwrap()
+    }
+
+    pub fn builder(&self) -> zbus::connection::Builder<'static> {
+        zbus::connection::Builder::address(self.address.as_str()).unwrap()
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}